@@ -1,11 +1,24 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use ruut_functions::F1D;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ruut_functions::{F1D, F3D};
 
 pub fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("easy as fuck", |b| b.iter(|| F1D::new("(sin(3+7)/8)+7^2")));
     c.bench_function("Creating F1D", |b| {
         b.iter(|| F1D::new("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)/cos(14x)+3/2-1/7"))
     });
+    let f = F3D::new("x+sin(x)/cos(y)+ln(4z)ln(7)+sin(x^2)^2+tan(14x)/cos(14x)+3/2-1/7").unwrap();
+    c.bench_function("Evaluating F3D", |b| {
+        b.iter(|| f.eval(black_box(0.3), black_box(-1.2), black_box(2.5)))
+    });
+    let compiled = f.compile();
+    c.bench_function("Evaluating compiled F3D", |b| {
+        b.iter(|| compiled.eval(black_box(&[0.3, -1.2, 2.5])))
+    });
+    c.bench_function("Compiling F3D", |b| b.iter(|| f.compile()));
+    let xs: Vec<f64> = (0..10_000).map(|i| i as f64 * 1e-3).collect();
+    c.bench_function("Evaluating F3D over 10k points", |b| {
+        b.iter(|| f.eval_many(black_box(&xs), &xs, &xs))
+    });
     // c.bench_function("create F1D", |b| {
     //     b.iter(|| f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)/cos(14x)+3/2-1/7"))
     // });
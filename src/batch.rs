@@ -0,0 +1,271 @@
+use crate::{
+    compile::{Compiled, Op},
+    F1D, F2D, F3D, FND,
+};
+
+// Points evaluated together, small enough for the buffers to stay in cache
+const CHUNK: usize = 256;
+
+// Where the value of each variable comes from
+pub(crate) enum Points<'a> {
+    // One slice per variable, all of the same length
+    Columns(&'a [&'a [f64]]),
+    // Tensor product of the slices, the last variable changes fastest
+    Grid(&'a [&'a [f64]]),
+}
+
+impl<'a> Points<'a> {
+    pub(crate) fn columns(cols: &'a [&'a [f64]], vars: usize) -> Self {
+        assert_eq!(cols.len(), vars, "One column per variable");
+        assert!(
+            cols.iter().all(|col| col.len() == cols[0].len()),
+            "Columns must have the same length"
+        );
+        Points::Columns(cols)
+    }
+
+    pub(crate) fn grid(axes: &'a [&'a [f64]], vars: usize) -> Self {
+        assert_eq!(axes.len(), vars, "One axis per variable");
+        Points::Grid(axes)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Points::Columns(cols) => cols.first().map_or(0, |col| col.len()),
+            Points::Grid(axes) => axes.iter().map(|axis| axis.len()).product(),
+        }
+    }
+
+    // Values of a variable for the points start..start + out.len()
+    fn load(&self, slot: usize, start: usize, out: &mut [f64]) {
+        match self {
+            Points::Columns(cols) => out.copy_from_slice(&cols[slot][start..start + out.len()]),
+            Points::Grid(axes) => {
+                let axis = axes[slot];
+                let stride: usize = axes[slot + 1..].iter().map(|axis| axis.len()).product();
+                for (i, val) in out.iter_mut().enumerate() {
+                    *val = axis[(start + i) / stride % axis.len()];
+                }
+            }
+        }
+    }
+}
+
+impl Compiled {
+    /// Evaluates the function over many points, `cols` holds a slice of values for each
+    /// variable in slot order
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// let f = f2d!("x+y^2").compile();
+    /// assert_eq!(f.eval_many(&[&[1., 2.], &[3., 4.]]), vec![10., 18.]);
+    /// ```
+    ///
+    /// # Panics
+    /// If there isn't one column per variable or the columns have different lengths
+    pub fn eval_many(&self, cols: &[&[f64]]) -> Vec<f64> {
+        self.batch(&Points::columns(cols, self.vars().len()))
+    }
+
+    /// Evaluates the function on the tensor product of `axes`, one axis per variable in slot
+    /// order. The result is row-major: the last variable changes fastest
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// let f = f2d!("10x+y").compile();
+    /// assert_eq!(f.eval_grid(&[&[1., 2.], &[3., 4., 5.]]), vec![13., 14., 15., 23., 24., 25.]);
+    /// ```
+    ///
+    /// # Panics
+    /// If there isn't one axis per variable
+    pub fn eval_grid(&self, axes: &[&[f64]]) -> Vec<f64> {
+        self.batch(&Points::grid(axes, self.vars().len()))
+    }
+
+    fn batch(&self, points: &Points) -> Vec<f64> {
+        let mut result = vec![0.; points.len()];
+        self.run_batch(points, 0, &mut result);
+        result
+    }
+
+    // Evaluates the points start..start + out.len(), running the tape once per chunk with
+    // each instruction over all the points of the chunk
+    pub(crate) fn run_batch(&self, points: &Points, start: usize, out: &mut [f64]) {
+        let mut stack = vec![vec![0.; CHUNK]; self.stack_size];
+        // Starting values of Sum and Product, so that results match eval bit for bit
+        let empty_sum: f64 = std::iter::empty::<f64>().sum();
+        let empty_product: f64 = std::iter::empty::<f64>().product();
+
+        for (i, out) in out.chunks_mut(CHUNK).enumerate() {
+            let start = start + i * CHUNK;
+            let n = out.len();
+            let mut top = 0;
+
+            for op in &self.tape {
+                match *op {
+                    Op::Var(slot) => {
+                        points.load(slot, start, &mut stack[top][..n]);
+                        top += 1;
+                    }
+                    Op::Const(val) => {
+                        stack[top][..n].fill(val);
+                        top += 1;
+                    }
+                    Op::Add(count) | Op::Mul(count) => {
+                        let add = matches!(op, Op::Add(_));
+                        let init = if add { empty_sum } else { empty_product };
+                        let first = top - count;
+                        if count == 0 {
+                            stack[first][..n].fill(init);
+                        } else {
+                            let (acc, rest) = stack[first..top].split_first_mut().unwrap();
+                            let acc = &mut acc[..n];
+                            for val in acc.iter_mut() {
+                                *val = if add { init + *val } else { init * *val };
+                            }
+                            for col in rest {
+                                for (val, el) in acc.iter_mut().zip(&col[..n]) {
+                                    if add {
+                                        *val += el;
+                                    } else {
+                                        *val *= el;
+                                    }
+                                }
+                            }
+                        }
+                        top = first + 1;
+                    }
+                    Op::Pow => {
+                        let (base, exp) = stack.split_at_mut(top - 1);
+                        for (val, exp) in base[top - 2][..n].iter_mut().zip(&exp[0][..n]) {
+                            *val = val.powf(*exp);
+                        }
+                        top -= 1;
+                    }
+                    Op::S(kind) => {
+                        for val in &mut stack[top - 1][..n] {
+                            *val = kind.apply(*val);
+                        }
+                    }
+                }
+            }
+
+            out.copy_from_slice(&stack[0][..n]);
+        }
+    }
+}
+
+impl F1D {
+    /// Evaluates function at every x
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("x^2").eval_many(&[1., 2., 3.]), vec![1., 4., 9.]);
+    /// ```
+    pub fn eval_many(&self, x: &[f64]) -> Vec<f64> {
+        self.compile().eval_many(&[x])
+    }
+}
+impl F2D {
+    /// Evaluates function at every (x[i], y[i])
+    ///
+    /// # Panics
+    /// If x and y have different lengths
+    pub fn eval_many(&self, x: &[f64], y: &[f64]) -> Vec<f64> {
+        self.compile().eval_many(&[x, y])
+    }
+    /// Evaluates function on the grid x × y, row-major (y changes fastest)
+    pub fn eval_grid(&self, x: &[f64], y: &[f64]) -> Vec<f64> {
+        self.compile().eval_grid(&[x, y])
+    }
+}
+impl F3D {
+    /// Evaluates function at every (x[i], y[i], z[i])
+    ///
+    /// # Panics
+    /// If x, y and z have different lengths
+    pub fn eval_many(&self, x: &[f64], y: &[f64], z: &[f64]) -> Vec<f64> {
+        self.compile().eval_many(&[x, y, z])
+    }
+    /// Evaluates function on the grid x × y × z, row-major (z changes fastest)
+    pub fn eval_grid(&self, x: &[f64], y: &[f64], z: &[f64]) -> Vec<f64> {
+        self.compile().eval_grid(&[x, y, z])
+    }
+}
+impl FND {
+    /// Evaluates function at every point, each variable comes with its column of values
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("theta x", &["theta", "x"]);
+    /// assert_eq!(f.eval_many(&[("x", &[1., 2.]), ("theta", &[3., 4.])]), vec![3., 8.]);
+    /// ```
+    ///
+    /// # Panics
+    /// If a variable of the function has no column or the columns have different lengths
+    pub fn eval_many(&self, val: &[(&str, &[f64])]) -> Vec<f64> {
+        self.compile().eval_many(&self.slots(val))
+    }
+    /// Evaluates function on the tensor product of the axes, row-major with the last variable
+    /// of the function changing fastest
+    ///
+    /// # Panics
+    /// If a variable of the function has no axis
+    pub fn eval_grid(&self, val: &[(&str, &[f64])]) -> Vec<f64> {
+        self.compile().eval_grid(&self.slots(val))
+    }
+
+    // Values ordered like the variables of the function
+    pub(crate) fn slots<'a>(&self, val: &[(&str, &'a [f64])]) -> Vec<&'a [f64]> {
+        self.vars
+            .iter()
+            .map(|var| {
+                val.iter()
+                    .find(|&x| x.0 == var)
+                    .unwrap_or_else(|| panic!("Missing values for variable `{}`", var))
+                    .1
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_batch() {
+    use crate::{f1d, f2d, f3d, fnd};
+    // NaN payloads depend on the instructions the compiler picked
+    let same = |a: f64, b: f64| a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan());
+
+    let xs: Vec<f64> = (0..1000).map(|i| -3. + i as f64 * 0.0071).collect();
+    let ys: Vec<f64> = xs.iter().map(|x| (x * 7.).sin()).collect();
+    let zs: Vec<f64> = xs.iter().map(|x| x * x - 1.).collect();
+
+    let f = f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+e^(-x/2)+3/2-1/7");
+    let many = f.eval_many(&xs);
+    for (x, val) in xs.iter().zip(&many) {
+        assert!(same(*val, f.eval(*x)));
+    }
+
+    let f = f3d!("xyz+(x+y)^(z-0.5)+atan(x/y)+acosh(z)");
+    let many = f.eval_many(&xs, &ys, &zs);
+    for i in 0..xs.len() {
+        assert!(same(many[i], f.eval(xs[i], ys[i], zs[i])));
+    }
+
+    let f = f2d!("x^2-y");
+    assert_eq!(
+        f.eval_grid(&[0., 1., 2.], &[10., 20.]),
+        vec![-10., -20., -9., -19., -6., -16.]
+    );
+    let grid = f3d!("100x+10y+z").eval_grid(&[1., 2.], &[3., 4., 5.], &[6., 7.]);
+    assert_eq!(grid.len(), 12);
+    assert_eq!(grid[0], 136.);
+    assert_eq!(grid[7], 237.);
+    assert_eq!(grid[11], 257.);
+
+    let f = fnd!("alpha-beta^2", &["alpha", "beta"]);
+    assert_eq!(
+        f.eval_grid(&[("beta", &[1., 2.]), ("alpha", &[10.])]),
+        vec![9., 6.]
+    );
+    assert!(f1d!("x").eval_many(&[]).is_empty());
+}
@@ -0,0 +1,248 @@
+use crate::{
+    simp::{from_factors, merge_terms, split_coefficient},
+    Func, F2D, F3D, FND,
+};
+
+impl Func {
+    // Expanded terms merged like in simp_add, with the factors free of var in the
+    // coefficients: (coefficient, other factors) pairs
+    fn groups_of(&self, var: &str) -> Vec<(Func, Func)> {
+        let terms = match self.expand() {
+            Func::Add(add) => add,
+            other => vec![other],
+        };
+        let free = |factor: &Func| !has_var(factor, var);
+        let mut groups: Vec<Func> = Vec::new();
+        for term in terms {
+            let term = coefficient_first(term, var);
+            let merged = groups
+                .iter()
+                .enumerate()
+                .find_map(|(i, group)| Some((i, merge_terms(group, &term, free)?)));
+            match merged {
+                Some((i, sum)) => groups[i] = coefficient_first(sum, var),
+                None => groups.push(term),
+            }
+        }
+        groups
+            .iter()
+            .map(|group| {
+                let (coef, rest) = split_coefficient(group, free);
+                (from_factors(coef), from_factors(rest))
+            })
+            .filter(|(coef, _)| *coef != 0)
+            .collect()
+    }
+
+    pub(crate) fn collect(&self, var: &str) -> Self {
+        let mut groups = self.groups_of(var);
+        // From the highest power of var, then the other factors
+        groups.sort_by_key(|(_, rest)| std::cmp::Reverse(degree(rest, var)));
+        let mut terms: Vec<Func> = groups.into_iter().map(|(coef, rest)| coef * rest).collect();
+        match terms.len() {
+            0 => Func::Num(0),
+            1 => terms.remove(0),
+            _ => Func::Add(terms),
+        }
+    }
+
+    // Coefficient of var^k at k, up to the highest power, None if a term isn't one of them
+    pub(crate) fn coefficients(&self, var: &str) -> Option<Vec<Self>> {
+        let powers = self
+            .groups_of(var)
+            .into_iter()
+            .map(|(coef, rest)| Some((degree(&rest, var)?, coef)))
+            .collect::<Option<Vec<_>>>()?;
+        let degree = powers.iter().map(|(exp, _)| *exp).max().unwrap_or(0);
+        let mut coefs = vec![Func::Num(0); degree + 1];
+        for (exp, coef) in powers {
+            coefs[exp] = coef;
+        }
+        Some(coefs)
+    }
+}
+
+// Factors free of var moved in front, where split_coefficient takes the coefficient from
+fn coefficient_first(term: Func, var: &str) -> Func {
+    match term {
+        Func::Mul(mul) => {
+            let (mut coef, rest): (Vec<Func>, Vec<Func>) =
+                mul.into_iter().partition(|factor| !has_var(factor, var));
+            coef.extend(rest);
+            Func::Mul(coef)
+        }
+        other => other,
+    }
+}
+
+fn has_var(func: &Func, var: &str) -> bool {
+    match func {
+        Func::Var(name) => name == var,
+        Func::Add(vec) | Func::Mul(vec) => vec.iter().any(|func| has_var(func, var)),
+        Func::Pow(base, exp) => has_var(base, var) || has_var(exp, var),
+        Func::S(_, arg) => has_var(arg, var),
+        _ => false,
+    }
+}
+
+// Exponent of var when the factors of a term are a power of it
+fn degree(factors: &Func, var: &str) -> Option<usize> {
+    match factors {
+        Func::Num(1) => Some(0),
+        Func::Var(name) if name == var => Some(1),
+        Func::Pow(base, exp) => match (&**base, &**exp) {
+            (Func::Var(name), Func::Num(exp)) if name == var && *exp > 0 => Some(*exp as usize),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl F2D {
+    /// Expands and groups the terms by power of var: a·x^2+b·x+c with a, b, c functions of
+    /// the other variable. Terms with other factors in var, like x·sin(x), are grouped by
+    /// those after the powers
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// let f = f2d!("(x+y)^2+xy+3x");
+    /// assert_eq!(f.coefficients('x'), Some(vec![f2d!("y^2"), f2d!("3+3y"), f2d!("1")]));
+    /// assert_eq!(f2d!("x sin(x)+x").coefficients('x'), None);
+    /// ```
+    ///
+    /// # Panics
+    /// If var isn't x or y
+    pub fn collect(&self, var: char) -> Self {
+        assert!(matches!(var, 'x' | 'y'), "{} is not a variable of F2D", var);
+        F2D(self.0.collect(&var.to_string()))
+    }
+
+    /// Coefficients of the powers of var in the expanded function, the one of var^k at k.
+    /// None if var isn't x or y, or if the function isn't a polynomial in var
+    pub fn coefficients(&self, var: char) -> Option<Vec<Self>> {
+        if !matches!(var, 'x' | 'y') {
+            return None;
+        }
+        let coefs = self.0.coefficients(&var.to_string())?;
+        Some(coefs.into_iter().map(F2D).collect())
+    }
+}
+impl F3D {
+    /// Expands and groups the terms by power of var: a·x^2+b·x+c with a, b, c functions of
+    /// the other variables. Terms with other factors in var are grouped by those after the
+    /// powers
+    ///
+    /// # Panics
+    /// If var isn't x, y or z
+    pub fn collect(&self, var: char) -> Self {
+        assert!(
+            matches!(var, 'x' | 'y' | 'z'),
+            "{} is not a variable of F3D",
+            var
+        );
+        F3D(self.0.collect(&var.to_string()))
+    }
+
+    /// Coefficients of the powers of var in the expanded function, the one of var^k at k.
+    /// None if var isn't x, y or z, or if the function isn't a polynomial in var
+    pub fn coefficients(&self, var: char) -> Option<Vec<Self>> {
+        if !matches!(var, 'x' | 'y' | 'z') {
+            return None;
+        }
+        let coefs = self.0.coefficients(&var.to_string())?;
+        Some(coefs.into_iter().map(F3D).collect())
+    }
+}
+impl FND {
+    /// Expands and groups the terms by power of var: a·v^2+b·v+c with a, b, c functions of
+    /// the other variables. Terms with other factors in var are grouped by those after the
+    /// powers
+    ///
+    /// # Panics
+    /// If var isn't a variable of the function
+    pub fn collect(&self, var: &str) -> Self {
+        assert!(
+            self.vars.iter().any(|name| name == var),
+            "{} is not a variable of the function",
+            var
+        );
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.collect(var),
+        }
+    }
+
+    /// Coefficients of the powers of var in the expanded function, the one of var^k at k.
+    /// None if var isn't a variable of the function, or if the function isn't a polynomial
+    /// in var
+    pub fn coefficients(&self, var: &str) -> Option<Vec<Self>> {
+        if !self.vars.iter().any(|name| name == var) {
+            return None;
+        }
+        let coefs = self.func.coefficients(var)?;
+        Some(
+            coefs
+                .into_iter()
+                .map(|func| FND {
+                    vars: self.vars.clone(),
+                    func,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn test_collect() {
+    use crate::{f2d, f3d, fnd};
+
+    let f = f2d!("x^2y+xy+2x^2+3");
+    assert_eq!(format!("{}", f.collect('x')), "(2+y)x^2+xy+3");
+    assert_eq!(
+        f.coefficients('x'),
+        Some(vec![f2d!("3"), f2d!("y"), f2d!("2+y")])
+    );
+    assert_eq!(
+        f.coefficients('y'),
+        Some(vec![f2d!("3+2x^2"), f2d!("x+x^2")])
+    );
+    assert_eq!(f2d!("sin(y)").coefficients('x'), Some(vec![f2d!("sin(y)")]));
+
+    // Not polynomials in x, or not a variable
+    for f in [
+        f2d!("x sin(x)+x"),
+        f2d!("x^2e^x+y"),
+        f2d!("sqrt(x)+x"),
+        f2d!("x/(x+y)"),
+        f2d!("y/x+x"),
+    ] {
+        assert_eq!(f.coefficients('x'), None, "{}", f);
+    }
+    assert_eq!(f2d!("x+y").coefficients('w'), None);
+
+    // Grouped by the other factors in x too
+    let f = f2d!("x sin(x)+y x sin(x)+x");
+    let collected = f.collect('x');
+    assert_eq!(collected, f2d!("x+(1+y)x sin(x)"));
+    assert!((collected.eval(0.7, -1.3) - f.eval(0.7, -1.3)).abs() < 1e-12);
+
+    let f = f3d!("(x+y)(x+z)-x^2");
+    assert_eq!(format!("{}", f.collect('x')), "x(y+z)+yz");
+    for (x, y, z) in [(0.3, -1.2, 2.), (1.5, 0.4, -0.7)] {
+        assert!((f.collect('x').eval(x, y, z) - f.eval(x, y, z)).abs() < 1e-12);
+    }
+    assert_eq!(f.coefficients('x'), Some(vec![f3d!("yz"), f3d!("y+z")]));
+
+    // Coefficients in the other variables
+    let vars = ["v", "k", "c"];
+    let f = fnd!("k(v+1)^2+cv", &vars);
+    assert_eq!(
+        f.coefficients("v"),
+        Some(vec![
+            fnd!("k", &vars),
+            fnd!("c+2k", &vars),
+            fnd!("k", &vars)
+        ])
+    );
+    assert_eq!(f.coefficients("x"), None);
+}
@@ -0,0 +1,228 @@
+use crate::{FType, Func, F1D, F2D, F3D, FND};
+
+// Stack depth that fits in an array, deeper functions fall back to a Vec
+const INLINE_STACK: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Function compiled into a flat instruction tape, for fast repeated evaluation.
+///
+/// Variables are resolved to slots (in the order of [`Compiled::vars`]) and params
+/// are captured with the value they had when compiling.
+/// ```
+/// use ruut_functions::{f3d, F3D};
+///
+/// let f = f3d!("x^2+sin(y)z");
+/// let compiled = f.compile();
+/// assert_eq!(compiled.eval(&[1., 2., 3.]), f.eval(1., 2., 3.));
+/// ```
+pub struct Compiled {
+    vars: Vec<String>,
+    pub(crate) tape: Vec<Op>,
+    pub(crate) stack_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Op {
+    Var(usize),
+    Const(f64),
+    // Number of operands on the stack
+    Add(usize),
+    Mul(usize),
+    Pow,
+    S(FType),
+}
+
+impl Compiled {
+    fn new(func: &Func, vars: &[&str]) -> Self {
+        let mut compiled = Compiled {
+            vars: vars.iter().map(|var| var.to_string()).collect(),
+            tape: Vec::new(),
+            stack_size: 0,
+        };
+        compiled.push(func, 0);
+        compiled
+    }
+
+    // Appends the instructions of func, depth is the stack size before them
+    fn push(&mut self, func: &Func, depth: usize) {
+        self.stack_size = self.stack_size.max(depth + 1);
+
+        if !has_vars(func) {
+            // Same operations as eval, so the folded value is identical
+            self.tape.push(Op::Const(func.eval(&[])));
+            return;
+        }
+
+        match func {
+            Func::Var(name) => {
+                let slot = self.vars.iter().position(|var| var == name).unwrap();
+                self.tape.push(Op::Var(slot));
+            }
+            Func::Add(add) => {
+                for (i, el) in add.iter().enumerate() {
+                    self.push(el, depth + i);
+                }
+                self.tape.push(Op::Add(add.len()));
+            }
+            Func::Mul(mul) => {
+                for (i, el) in mul.iter().enumerate() {
+                    self.push(el, depth + i);
+                }
+                self.tape.push(Op::Mul(mul.len()));
+            }
+            Func::Pow(base, exp) => {
+                self.push(base, depth);
+                self.push(exp, depth + 1);
+                self.tape.push(Op::Pow);
+            }
+            Func::S(kind, arg) => {
+                self.push(arg, depth);
+                self.tape.push(Op::S(*kind));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Evaluates the function, `vals` holds the value of each variable in order
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("theta x+v_x^2", &["theta", "x", "v_x"]);
+    /// assert_eq!(f.compile().eval(&[2., 3., 4.]), 22.);
+    /// ```
+    ///
+    /// # Panics
+    /// If `vals` has fewer values than the function has variables
+    pub fn eval(&self, vals: &[f64]) -> f64 {
+        if self.stack_size <= INLINE_STACK {
+            self.run(vals, &mut [0.; INLINE_STACK])
+        } else {
+            self.run(vals, &mut vec![0.; self.stack_size])
+        }
+    }
+
+    /// Variables in slot order
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    fn run(&self, vals: &[f64], stack: &mut [f64]) -> f64 {
+        let mut top = 0;
+        for op in &self.tape {
+            match *op {
+                Op::Var(slot) => {
+                    stack[top] = vals[slot];
+                    top += 1;
+                }
+                Op::Const(val) => {
+                    stack[top] = val;
+                    top += 1;
+                }
+                Op::Add(len) => {
+                    let start = top - len;
+                    stack[start] = stack[start..top].iter().sum::<f64>();
+                    top = start + 1;
+                }
+                Op::Mul(len) => {
+                    let start = top - len;
+                    stack[start] = stack[start..top].iter().product::<f64>();
+                    top = start + 1;
+                }
+                Op::Pow => {
+                    top -= 1;
+                    stack[top - 1] = stack[top - 1].powf(stack[top]);
+                }
+                Op::S(kind) => stack[top - 1] = kind.apply(stack[top - 1]),
+            }
+        }
+        stack[0]
+    }
+}
+
+pub(crate) fn has_vars(func: &Func) -> bool {
+    match func {
+        Func::Var(_) => true,
+        Func::Add(vec) | Func::Mul(vec) => vec.iter().any(has_vars),
+        Func::Pow(base, exp) => has_vars(base) || has_vars(exp),
+        Func::S(_, arg) => has_vars(arg),
+        _ => false,
+    }
+}
+
+impl F1D {
+    /// Compiles the function for fast repeated evaluation, with slot x
+    pub fn compile(&self) -> Compiled {
+        Compiled::new(&self.0, &["x"])
+    }
+}
+impl F2D {
+    /// Compiles the function for fast repeated evaluation, with slots x, y
+    pub fn compile(&self) -> Compiled {
+        Compiled::new(&self.0, &["x", "y"])
+    }
+}
+impl F3D {
+    /// Compiles the function for fast repeated evaluation, with slots x, y, z
+    pub fn compile(&self) -> Compiled {
+        Compiled::new(&self.0, &["x", "y", "z"])
+    }
+}
+impl FND {
+    /// Compiles the function for fast repeated evaluation, slots follow the variables order
+    pub fn compile(&self) -> Compiled {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        Compiled::new(&self.func, &vars)
+    }
+}
+
+#[test]
+fn test_compile() {
+    use crate::{f1d, f3d, fnd};
+
+    let funcs = [
+        f3d!("x+sin(x)/cos(y)+ln(4z)ln(7)+sin(x^2)^2+tan(14x)/cos(14x)+3/2-1/7"),
+        f3d!("e^(-xyz)+sqrt(abs(x-y))+asinh(z)atan(x/y)"),
+        f3d!("(x+y)^(z-0.5)+cot(x)sec(y)csc(z)+acos(x/10)"),
+        f3d!("ln(x)+sinh(y)cosh(z)tanh(x)coth(y)sech(z)csch(x)+x^pi"),
+    ];
+    let points = [
+        (0.3, -1.2, 2.5),
+        (1., 2., 3.),
+        (-0.7, 0.1, -4.2),
+        (5e3, 1e-4, 0.),
+    ];
+    for func in &funcs {
+        let compiled = func.compile();
+        for (x, y, z) in points {
+            assert_eq!(
+                compiled.eval(&[x, y, z]).to_bits(),
+                func.eval(x, y, z).to_bits()
+            );
+        }
+    }
+
+    let mut f = f1d!("[a]x^2+sin([a])");
+    f.set_par("a", 1.5);
+    let compiled = f.compile();
+    f.set_par("a", 3.);
+    assert_eq!(compiled.eval(&[2.]), 6. + 1.5f64.sin());
+
+    // Deeper than the inline stack
+    let deep = (1..40)
+        .map(|i| format!("{}", i))
+        .collect::<Vec<_>>()
+        .join("+x*(");
+    let deep = F1D::new(&format!("{}{}", deep, ")".repeat(38))).unwrap();
+    assert_eq!(
+        deep.compile().eval(&[0.7]).to_bits(),
+        deep.eval(0.7).to_bits()
+    );
+
+    let f = fnd!("alpha^2-beta/alpha", &["beta", "alpha"]);
+    let compiled = f.compile();
+    assert_eq!(compiled.vars(), ["beta", "alpha"]);
+    assert_eq!(
+        compiled.eval(&[3., 2.]),
+        f.eval(&[("alpha", 2.), ("beta", 3.)])
+    );
+}
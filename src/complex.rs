@@ -0,0 +1,420 @@
+use std::{
+    f64::consts::{E, PI},
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::{FType, Func, F1D, F2D, F3D, FND};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Complex number re + i im
+/// ```
+/// use ruut_functions::{f1d, Complex, F1D};
+///
+/// // Transfer function at s = i
+/// let h = f1d!("1/(x^2+x+1)").eval_complex(Complex::new(0., 1.), &[]);
+/// assert!((h.re - 0.).abs() < 1e-15 && (h.im + 1.).abs() < 1e-15);
+/// ```
+pub struct Complex {
+    /// Real part
+    pub re: f64,
+    /// Imaginary part
+    pub im: f64,
+}
+
+impl Complex {
+    /// The imaginary unit
+    pub const I: Complex = Complex { re: 0., im: 1. };
+
+    /// Creates re + i im
+    pub fn new(re: f64, im: f64) -> Self {
+        Complex { re, im }
+    }
+    /// Modulus
+    pub fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+    /// Argument in (-pi, pi]
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+    /// Exponential
+    pub fn exp(self) -> Self {
+        let norm = self.re.exp();
+        // Keeps e^x real for real x, even when x is huge
+        if self.im == 0. {
+            return Complex::new(norm, self.im);
+        }
+        Complex::new(norm * self.im.cos(), norm * self.im.sin())
+    }
+    /// Principal logarithm, cut along the negative real axis
+    pub fn ln(self) -> Self {
+        Complex::new(self.norm().ln(), self.arg())
+    }
+    /// Principal square root, cut along the negative real axis
+    pub fn sqrt(self) -> Self {
+        if self.re == 0. && self.im == 0. {
+            return Complex::new(0., self.im);
+        }
+        // Avoids the cancellation of sqrt((|z| +- re) / 2)
+        let t = ((self.norm() + self.re.abs()) / 2.).sqrt();
+        if self.re >= 0. {
+            Complex::new(t, self.im / (2. * t))
+        } else {
+            Complex::new(self.im.abs() / (2. * t), t.copysign(self.im))
+        }
+    }
+    /// Principal power, e^(exp ln(self))
+    pub fn pow(self, exp: Self) -> Self {
+        if exp.im == 0. && exp.re.fract() == 0. && exp.re.abs() <= i32::MAX as f64 {
+            return self.powi(exp.re as i32);
+        }
+        if self.re == 0. && self.im == 0. {
+            return if exp.re > 0. {
+                Complex::new(0., 0.)
+            } else {
+                Complex::new(f64::NAN, f64::NAN)
+            };
+        }
+        (exp * self.ln()).exp()
+    }
+    /// Integer power by repeated squaring, exact for real and imaginary bases
+    pub fn powi(self, exp: i32) -> Self {
+        let mut result = Complex::new(1., 0.);
+        let mut base = self;
+        let mut n = exp.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        if exp < 0 {
+            result.recip()
+        } else {
+            result
+        }
+    }
+    /// 1 / self
+    pub fn recip(self) -> Self {
+        Complex::new(1., 0.) / self
+    }
+
+    /// Sine
+    pub fn sin(self) -> Self {
+        Complex::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+    /// Cosine
+    pub fn cos(self) -> Self {
+        Complex::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+    /// Tangent
+    pub fn tan(self) -> Self {
+        self.sin() / self.cos()
+    }
+    /// Hyperbolic sine
+    pub fn sinh(self) -> Self {
+        Complex::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+    /// Hyperbolic cosine
+    pub fn cosh(self) -> Self {
+        Complex::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+    /// Hyperbolic tangent
+    pub fn tanh(self) -> Self {
+        self.sinh() / self.cosh()
+    }
+    /// Principal arcsine, cuts along (-inf, -1) and (1, inf)
+    pub fn asin(self) -> Self {
+        // -i ln(iz + sqrt(1 - z^2))
+        let one = Complex::new(1., 0.);
+        -Complex::I * (Complex::I * self + (one - self * self).sqrt()).ln()
+    }
+    /// Principal arccosine, cuts along (-inf, -1) and (1, inf)
+    pub fn acos(self) -> Self {
+        // -i ln(z + i sqrt(1 - z^2))
+        let one = Complex::new(1., 0.);
+        -Complex::I * (self + Complex::I * (one - self * self).sqrt()).ln()
+    }
+    /// Principal arctangent, cuts along (-i inf, -i) and (i, i inf)
+    pub fn atan(self) -> Self {
+        // (ln(1 + iz) - ln(1 - iz)) / 2i
+        let one = Complex::new(1., 0.);
+        let iz = Complex::I * self;
+        ((one + iz).ln() - (one - iz).ln()) / Complex::new(0., 2.)
+    }
+    /// Principal inverse hyperbolic sine, cuts along (-i inf, -i) and (i, i inf)
+    pub fn asinh(self) -> Self {
+        // ln(z + sqrt(z^2 + 1))
+        (self + (self * self + Complex::new(1., 0.)).sqrt()).ln()
+    }
+    /// Principal inverse hyperbolic cosine, cut along (-inf, 1)
+    pub fn acosh(self) -> Self {
+        // 2 ln(sqrt((z + 1) / 2) + sqrt((z - 1) / 2))
+        let one = Complex::new(1., 0.);
+        let two = Complex::new(2., 0.);
+        two * (((self + one) / two).sqrt() + ((self - one) / two).sqrt()).ln()
+    }
+    /// Principal inverse hyperbolic tangent, cuts along (-inf, -1) and (1, inf)
+    pub fn atanh(self) -> Self {
+        // (ln(1 + z) - ln(1 - z)) / 2
+        let one = Complex::new(1., 0.);
+        ((one + self).ln() - (one - self).ln()) / Complex::new(2., 0.)
+    }
+
+    fn apply(self, kind: FType) -> Self {
+        match kind {
+            FType::Sin => self.sin(),
+            FType::Cos => self.cos(),
+            FType::Tan => self.tan(),
+            FType::Cot => self.tan().recip(),
+            FType::Sec => self.cos().recip(),
+            FType::Csc => self.sin().recip(),
+            FType::ASin => self.asin(),
+            FType::ACos => self.acos(),
+            FType::ATan => self.atan(),
+            FType::Sinh => self.sinh(),
+            FType::Cosh => self.cosh(),
+            FType::Tanh => self.tanh(),
+            FType::Coth => self.tanh().recip(),
+            FType::Sech => self.cosh().recip(),
+            FType::Csch => self.sinh().recip(),
+            FType::ASinh => self.asinh(),
+            FType::ACosh => self.acosh(),
+            FType::ATanh => self.atanh(),
+            FType::Abs => Complex::new(self.norm(), 0.),
+            FType::Ln => self.ln(),
+        }
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Complex::new(re, 0.)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        // Smith's algorithm, avoids overflowing |rhs|^2
+        if rhs.im == 0. {
+            Complex::new(self.re / rhs.re, self.im / rhs.re)
+        } else if rhs.re.abs() >= rhs.im.abs() {
+            let ratio = rhs.im / rhs.re;
+            let den = rhs.re + rhs.im * ratio;
+            Complex::new(
+                (self.re + self.im * ratio) / den,
+                (self.im - self.re * ratio) / den,
+            )
+        } else {
+            let ratio = rhs.re / rhs.im;
+            let den = rhs.re * ratio + rhs.im;
+            Complex::new(
+                (self.re * ratio + self.im) / den,
+                (self.im * ratio - self.re) / den,
+            )
+        }
+    }
+}
+impl Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.im < 0. {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+impl Func {
+    fn eval_complex(&self, vars: &[(&str, Complex)], params: &[(&str, Complex)]) -> Complex {
+        match self {
+            Func::Var(var) => vars
+                .iter()
+                .find(|&x| x.0 == var)
+                .map_or(Complex::new(f64::NAN, f64::NAN), |x| x.1),
+            Func::Param(name, val) => params
+                .iter()
+                .find(|&x| x.0 == name)
+                .map_or(Complex::from(*val), |x| x.1),
+            Func::E => Complex::from(E),
+            Func::PI => Complex::from(PI),
+            Func::Num(_) | Func::Float(_) | Func::Rational(_) => Complex::from(self.eval(&[])),
+            Func::Add(add) => add.iter().fold(Complex::from(0.), |acc, term| {
+                acc + term.eval_complex(vars, params)
+            }),
+            Func::Mul(mul) => mul.iter().fold(Complex::from(1.), |acc, term| {
+                acc * term.eval_complex(vars, params)
+            }),
+            Func::Pow(base, exp) => {
+                let exp = exp.eval_complex(vars, params);
+                match **base {
+                    Func::E => exp.exp(),
+                    _ => base.eval_complex(vars, params).pow(exp),
+                }
+            }
+            Func::S(kind, arg) => arg.eval_complex(vars, params).apply(*kind),
+        }
+    }
+}
+
+impl F1D {
+    /// Evaluates function at a complex x, params not in `params` keep their real value
+    pub fn eval_complex(&self, x: Complex, params: &[(&str, Complex)]) -> Complex {
+        self.0.eval_complex(&[("x", x)], params)
+    }
+}
+impl F2D {
+    /// Evaluates function at complex (x,y), params not in `params` keep their real value
+    pub fn eval_complex(&self, x: Complex, y: Complex, params: &[(&str, Complex)]) -> Complex {
+        self.0.eval_complex(&[("x", x), ("y", y)], params)
+    }
+}
+impl F3D {
+    /// Evaluates function at complex (x,y,z), params not in `params` keep their real value
+    pub fn eval_complex(
+        &self,
+        x: Complex,
+        y: Complex,
+        z: Complex,
+        params: &[(&str, Complex)],
+    ) -> Complex {
+        self.0.eval_complex(&[("x", x), ("y", y), ("z", z)], params)
+    }
+}
+impl FND {
+    /// Evaluates function at complex values, params not in `params` keep their real value
+    /// ```
+    /// use ruut_functions::{fnd, Complex, FND};
+    ///
+    /// let f = fnd!("[k]/(1+tau s)", &["s", "tau"]);
+    /// let s = Complex::new(0., 2.);
+    /// let h = f.eval_complex(&[("s", s), ("tau", 0.5.into())], &[("k", 2.0.into())]);
+    /// assert!((h.re - 1.).abs() < 1e-15 && (h.im + 1.).abs() < 1e-15);
+    /// ```
+    pub fn eval_complex(&self, vars: &[(&str, Complex)], params: &[(&str, Complex)]) -> Complex {
+        self.func.eval_complex(vars, params)
+    }
+}
+
+#[test]
+fn test_complex() {
+    use crate::F1D;
+    use crate::{f1d, f2d, f3d};
+
+    let close = |a: Complex, b: Complex| (a - b).norm() <= 1e-12 * (1. + b.norm());
+    let c = Complex::new;
+
+    // Same as eval on the real domain
+    let funcs = [
+        f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)cos(14x)+3/2-1/7"),
+        f1d!("e^(-x/2)+sqrt(x)+asin(x/4)acos(x/5)+atan(x)+abs(x-3)"),
+        f1d!("sinh(x)cosh(x)tanh(x)+coth(x)-sech(x)+csch(x)+asinh(x)+acosh(x+1)+atanh(x/3)"),
+        f1d!("cot(x)+sec(x)^2-csc(3x)+x^x+2^x+x^(-3)+pi^e"),
+    ];
+    for func in &funcs {
+        for x in [0.1, 0.5, 1.2, 2.7] {
+            let val = func.eval_complex(c(x, 0.), &[]);
+            assert!(close(val, c(func.eval(x), 0.)), "{} at {}", func, x);
+        }
+    }
+
+    // Principal branches
+    assert!(close(
+        f1d!("ln(x)").eval_complex(c(-1., 0.), &[]),
+        c(0., PI)
+    ));
+    assert!(close(
+        f1d!("sqrt(x)").eval_complex(c(-4., 0.), &[]),
+        c(0., 2.)
+    ));
+    assert!(close(f1d!("x^2").eval_complex(Complex::I, &[]), c(-1., 0.)));
+    assert!(close(
+        f1d!("acosh(x)").eval_complex(c(0., 0.), &[]),
+        c(0., PI / 2.)
+    ));
+    assert!(close(
+        f1d!("atanh(x)").eval_complex(c(0., 1.), &[]),
+        c(0., PI / 4.)
+    ));
+    assert!(close(
+        f1d!("asin(x)").eval_complex(c(0., 1.), &[]),
+        c(0., 2f64.sqrt().ln_1p())
+    ));
+    assert!(close(
+        f1d!("e^(pi x)").eval_complex(Complex::I, &[]),
+        c(-1., 0.)
+    ));
+    for z in [c(0.3, 0.4), c(-1.5, 2.), c(2., -0.7), c(-0.2, -3.)] {
+        for (inverse, func) in [
+            ("asin", "sin"),
+            ("acos", "cos"),
+            ("atan", "tan"),
+            ("asinh", "sinh"),
+            ("acosh", "cosh"),
+            ("atanh", "tanh"),
+        ] {
+            let round_trip = F1D::new(&format!("{}({}(x))", func, inverse)).unwrap();
+            assert!(close(round_trip.eval_complex(z, &[]), z));
+        }
+    }
+
+    let f = f2d!("[a]x+y");
+    assert!(close(f.eval_complex(c(1., 0.), c(0., 1.), &[]), c(0., 1.)));
+    assert!(close(
+        f.eval_complex(c(1., 0.), c(0., 1.), &[("a", c(0., 2.))]),
+        c(0., 3.)
+    ));
+    assert!(close(
+        f3d!("xyz").eval_complex(Complex::I, Complex::I, Complex::I, &[]),
+        c(0., -1.)
+    ));
+    assert_eq!(format!("{}", c(1., -2.)), "1-2i");
+}
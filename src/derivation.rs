@@ -1,4 +1,9 @@
-use crate::{FType, Func, F1D, F2D, F3D, FND};
+use std::collections::HashMap;
+
+use crate::{
+    simp::{from_float, simp_node},
+    FType, Func, F1D, F2D, F3D, FND,
+};
 
 impl F1D {
     /// Computes the nth-derivative
@@ -11,7 +16,7 @@ impl F1D {
     /// assert_eq!(df.derive(1), f.derive(2));
     /// ```
     pub fn derive(&self, order: usize) -> Self {
-        F1D(self.0.derive_nth('x', order))
+        F1D(self.0.derive_nth("x", order))
     }
 }
 impl F2D {
@@ -21,7 +26,31 @@ impl F2D {
     /// assert_eq!(f2d!("x+y^2").derive('y', 2), f2d!("2"));
     /// ```
     pub fn derive(&self, var: char, order: usize) -> Self {
-        F2D(self.0.derive_nth(var, order))
+        F2D(self.0.derive_nth(&var.to_string(), order))
+    }
+    /// Computes a mixed partial derivative, deriving in order by each variable
+    /// ```
+    /// use ruut_functions::{f2d,F2D};
+    /// assert_eq!(f2d!("x^3y^2").derive_multi(&[('x', 2), ('y', 1)]), f2d!("12xy"));
+    /// ```
+    pub fn derive_multi(&self, orders: &[(char, usize)]) -> Self {
+        let orders: Vec<(String, usize)> = orders
+            .iter()
+            .map(|(var, order)| (var.to_string(), *order))
+            .collect();
+        F2D(self.0.derive_multi(&orders))
+    }
+    /// Computes every partial derivative up to `order`, each one is derived from a partial
+    /// derivative one order lower. Multi-indices are [x order, y order]
+    /// ```
+    /// use ruut_functions::{f2d,F2D};
+    /// let partials = f2d!("x^2y").partials(3);
+    /// assert_eq!(partials.get(&[1, 1]), Some(&f2d!("2x")));
+    /// assert_eq!(partials.get(&[2, 1]), Some(&f2d!("2")));
+    /// assert_eq!(partials.len(), 10);
+    /// ```
+    pub fn partials(&self, order: usize) -> Partials<Self> {
+        Partials::new(self.0.partials(&["x", "y"], order), F2D)
     }
     /// Computes the gradient
     /// ```
@@ -46,6 +75,23 @@ impl F2D {
             vec![self.derive('y', 1).derive('x', 1), self.derive('y', 2)],
         ]
     }
+    /// Computes the laplacian
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    /// assert_eq!(f2d!("x^3+xy^2").laplacian(), f2d!("8x"));
+    /// ```
+    pub fn laplacian(&self) -> Self {
+        F2D(self.0.laplacian(&["x", "y"]))
+    }
+    /// Computes the derivative along `dir` (not normalized), the dot product of the gradient
+    /// with `dir`
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    /// assert_eq!(f2d!("xy").directional_derivative(&[2., -1.]), f2d!("2y-x"));
+    /// ```
+    pub fn directional_derivative(&self, dir: &[f64; 2]) -> Self {
+        F2D(self.0.directional_derivative(&["x", "y"], dir))
+    }
 }
 
 impl F3D {
@@ -55,7 +101,20 @@ impl F3D {
     /// assert_eq!(f3d!("x+zy^2").derive('y', 2), f3d!("2z"));
     /// ```
     pub fn derive(&self, var: char, order: usize) -> Self {
-        F3D(self.0.derive_nth(var, order))
+        F3D(self.0.derive_nth(&var.to_string(), order))
+    }
+    /// Computes a mixed partial derivative, deriving in order by each variable
+    pub fn derive_multi(&self, orders: &[(char, usize)]) -> Self {
+        let orders: Vec<(String, usize)> = orders
+            .iter()
+            .map(|(var, order)| (var.to_string(), *order))
+            .collect();
+        F3D(self.0.derive_multi(&orders))
+    }
+    /// Computes every partial derivative up to `order`, each one is derived from a partial
+    /// derivative one order lower. Multi-indices are [x order, y order, z order]
+    pub fn partials(&self, order: usize) -> Partials<Self> {
+        Partials::new(self.0.partials(&["x", "y", "z"], order), F3D)
     }
     /// Computes the gradient
     /// ```
@@ -86,39 +145,79 @@ impl F3D {
             vec![dz.derive('x', 1), dz.derive('y', 1), dz.derive('z', 1)],
         ]
     }
+    /// Computes the laplacian
+    /// ```
+    /// use ruut_functions::{f3d, F3D};
+    /// assert_eq!(f3d!("x^2+y^2z+z^3").laplacian(), f3d!("2+2z+6z"));
+    /// ```
+    pub fn laplacian(&self) -> Self {
+        F3D(self.0.laplacian(&["x", "y", "z"]))
+    }
+    /// Computes the derivative along `dir` (not normalized), the dot product of the gradient
+    /// with `dir`
+    pub fn directional_derivative(&self, dir: &[f64; 3]) -> Self {
+        F3D(self.0.directional_derivative(&["x", "y", "z"], dir))
+    }
 }
 
 impl FND {
     /// Computes the nth-derivative wrt a variable
     /// ```
     /// use ruut_functions::{fnd,FND};
-    /// let vars = ['f', 'z'];
-    /// assert_eq!(fnd!("f^2+z", &vars).derive('f', 1), fnd!("2f", &vars));
+    /// let vars = ["f", "z"];
+    /// assert_eq!(fnd!("f^2+z", &vars).derive("f", 1), fnd!("2f", &vars));
     /// ```
-    pub fn derive(&self, var: char, order: usize) -> Self {
+    pub fn derive(&self, var: &str, order: usize) -> Self {
         FND {
             vars: self.vars.clone(),
             func: self.func.derive_nth(var, order),
         }
     }
 
+    /// Computes a mixed partial derivative, deriving in order by each variable
+    /// ```
+    /// use ruut_functions::{fnd,FND};
+    /// let vars = ["u", "v"];
+    /// assert_eq!(fnd!("u^2e^v", &vars).derive_multi(&[("u", 1), ("v", 2)]), fnd!("2ue^v", &vars));
+    /// ```
+    pub fn derive_multi(&self, orders: &[(&str, usize)]) -> Self {
+        let orders: Vec<(String, usize)> = orders
+            .iter()
+            .map(|(var, order)| (var.to_string(), *order))
+            .collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.derive_multi(&orders),
+        }
+    }
+
+    /// Computes every partial derivative up to `order`, each one is derived from a partial
+    /// derivative one order lower. Multi-indices follow the order of the variables
+    pub fn partials(&self, order: usize) -> Partials<Self> {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        Partials::new(self.func.partials(&vars, order), |func| FND {
+            vars: self.vars.clone(),
+            func,
+        })
+    }
+
     /// Computes the gradient
     /// ```
     /// use ruut_functions::{fnd,FND};
-    /// let vars = ['f', 'z'];
+    /// let vars = ["f", "z"];
     /// assert_eq!(fnd!("f^2+z", &vars).gradient(), vec![fnd!("2f", &vars), fnd!("1", &vars)]);
     /// ```
     pub fn gradient(&self) -> Vec<Self> {
         let mut result = Vec::with_capacity(self.vars.len());
         for var in &self.vars {
-            result.push(self.derive(*var, 1));
+            result.push(self.derive(var, 1));
         }
         result
     }
     /// Computes the hessian
     /// ```
     /// use ruut_functions::{fnd,FND};
-    /// let vars = ['f', 'z'];
+    /// let vars = ["f", "z"];
     /// assert_eq!(fnd!("f^3+zf", &vars).hessian(), vec![vec![fnd!("6f", &vars), fnd!("1", &vars)],
     ///                                                  vec![fnd!("1", &vars), fnd!("0", &vars)]]);
     /// ```
@@ -128,40 +227,196 @@ impl FND {
         // first derivative
         let mut first_deriv = Vec::with_capacity(self.vars.len());
         for var in &self.vars {
-            first_deriv.push(self.derive(*var, 1));
+            first_deriv.push(self.derive(var, 1));
         }
 
         for el in first_deriv {
             let mut gradient = Vec::with_capacity(self.vars.len());
 
             for var in &self.vars {
-                gradient.push(el.derive(*var, 1))
+                gradient.push(el.derive(var, 1))
             }
             result.push(gradient);
         }
 
         result
     }
+    /// Computes the laplacian
+    /// ```
+    /// use ruut_functions::{fnd,FND};
+    /// let vars = ["u", "v"];
+    /// assert_eq!(fnd!("u^2v^2", &vars).laplacian(), fnd!("2v^2+2u^2", &vars));
+    /// ```
+    pub fn laplacian(&self) -> Self {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.laplacian(&vars),
+        }
+    }
+    /// Computes the derivative along `dir` (not normalized), `dir` follows the order of the
+    /// variables
+    ///
+    /// # Panics
+    /// If `dir` doesn't have one component per variable
+    pub fn directional_derivative(&self, dir: &[f64]) -> Self {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.directional_derivative(&vars, dir),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// Partial derivatives of a function up to some order, by multi-index: the i-th entry of a
+/// multi-index is the order of derivation in the i-th variable
+pub struct Partials<T> {
+    derivs: Vec<(Vec<usize>, T)>,
+    positions: HashMap<Vec<usize>, usize>,
+}
+
+impl<T> Partials<T> {
+    fn new(derivs: Vec<(Vec<usize>, Func)>, wrap: impl Fn(Func) -> T) -> Self {
+        let positions = derivs
+            .iter()
+            .enumerate()
+            .map(|(i, (index, _))| (index.clone(), i))
+            .collect();
+        Partials {
+            derivs: derivs
+                .into_iter()
+                .map(|(index, func)| (index, wrap(func)))
+                .collect(),
+            positions,
+        }
+    }
+
+    /// Returns the partial derivative of a multi-index, None if its order is too high
+    pub fn get(&self, index: &[usize]) -> Option<&T> {
+        self.positions.get(index).map(|&i| &self.derivs[i].1)
+    }
+
+    /// Number of partial derivatives, the function itself included
+    pub fn len(&self) -> usize {
+        self.derivs.len()
+    }
+
+    /// Always false, the function itself is included
+    pub fn is_empty(&self) -> bool {
+        self.derivs.is_empty()
+    }
+
+    /// Iterates over multi-indices and partial derivatives, by increasing order
+    pub fn iter(&self) -> impl Iterator<Item = (&[usize], &T)> {
+        self.derivs
+            .iter()
+            .map(|(index, der)| (index.as_slice(), der))
+    }
+}
+
+/// Computes the divergence of a vector field of 3 components
+/// ```
+/// use ruut_functions::{divergence, f3d, F3D};
+/// assert_eq!(divergence(&[f3d!("xy"), f3d!("yz"), f3d!("zx")]), f3d!("y+z+x"));
+/// ```
+pub fn divergence(field: &[F3D; 3]) -> F3D {
+    let mut result: Func = ["x", "y", "z"]
+        .iter()
+        .zip(field)
+        .map(|(var, comp)| comp.0.derive(var))
+        .sum();
+    simp_node(&mut result);
+    F3D(result)
+}
+
+/// Computes the curl of a vector field of 3 components
+/// ```
+/// use ruut_functions::{curl, f3d, F3D};
+/// assert_eq!(
+///     curl(&[f3d!("-y"), f3d!("x"), f3d!("0")]),
+///     [f3d!("0"), f3d!("0"), f3d!("2")]
+/// );
+/// ```
+pub fn curl(field: &[F3D; 3]) -> [F3D; 3] {
+    let [F3D(x), F3D(y), F3D(z)] = field;
+    [
+        z.derive("y") - y.derive("z"),
+        x.derive("z") - z.derive("x"),
+        y.derive("x") - x.derive("y"),
+    ]
+    .map(|mut comp| {
+        simp_node(&mut comp);
+        F3D(comp)
+    })
 }
 
 impl Func {
-    fn derive_nth(&self, var: char, order: usize) -> Self {
+    fn derive_multi(&self, orders: &[(String, usize)]) -> Self {
+        let mut result = self.clone();
+        for (var, order) in orders {
+            result = result.derive_nth(var, *order);
+        }
+        result
+    }
+
+    // All partials up to order by increasing order. Each multi-index is reached once, from
+    // the index with one order less in its last derived variable
+    pub(crate) fn partials(&self, vars: &[&str], order: usize) -> Vec<(Vec<usize>, Self)> {
+        let mut result = vec![(vec![0; vars.len()], self.clone())];
+        let mut level = 0;
+        for _ in 0..order {
+            let end = result.len();
+            for i in level..end {
+                let last = result[i].0.iter().rposition(|&n| n > 0).unwrap_or(0);
+                for (slot, var) in vars.iter().enumerate().skip(last) {
+                    let mut index = result[i].0.clone();
+                    index[slot] += 1;
+                    let der = result[i].1.derive(var);
+                    result.push((index, der));
+                }
+            }
+            level = end;
+        }
+        result
+    }
+
+    // Trace of the hessian, only the diagonal is derived
+    fn laplacian(&self, vars: &[&str]) -> Self {
+        let mut result: Func = vars.iter().map(|var| self.derive_nth(var, 2)).sum();
+        simp_node(&mut result);
+        result
+    }
+
+    fn directional_derivative(&self, vars: &[&str], dir: &[f64]) -> Self {
+        assert_eq!(dir.len(), vars.len(), "One component per variable");
+        let mut result: Func = vars
+            .iter()
+            .zip(dir)
+            .filter(|(_, &val)| val != 0.)
+            .map(|(var, &val)| from_float(val) * self.derive(var))
+            .sum();
+        simp_node(&mut result);
+        result
+    }
+
+    pub(crate) fn derive_nth(&self, var: &str, order: usize) -> Self {
         let mut result = self.clone();
         for _ in 1..=order {
             result = result.derive(var);
         }
         result
     }
-    fn derive(&self, var: char) -> Self {
+    fn derive(&self, var: &str) -> Self {
         let res = match self {
-            Self::Var(char) => {
-                if *char == var {
+            Self::Var(name) => {
+                if name == var {
                     Self::Num(1)
                 } else {
                     Self::Num(0)
                 }
             }
-            Self::Num(_) | Self::Param(..) => Self::Num(0),
+            Self::Num(_) | Self::Float(_) | Self::Rational(_) | Self::Param(..) => Self::Num(0),
             Self::E | Self::PI => Self::Num(0),
             Self::Add(add) => add.iter().map(|term| term.derive(var)).sum::<Self>(),
             Self::Mul(mul) => {
@@ -182,8 +437,13 @@ impl Func {
                 if let Func::E = **base {
                     return exp.derive(var) * self.clone();
                 }
-                if let Func::Num(exp_val) = **exp {
-                    return exp_val * base.derive(var) * base.clone().powi(exp_val - 1);
+                if let Func::Num(_) | Func::Rational(_) = **exp {
+                    return *exp.clone() * base.derive(var) * base.clone().pow(*exp.clone() - 1);
+                }
+                if let Func::Float(exp_val) = **exp {
+                    return Func::Float(exp_val)
+                        * base.derive(var)
+                        * base.clone().pow(Func::Float(exp_val - 1.));
                 }
                 (Func::E.pow(*exp.clone() * Self::S(FType::Ln, base.clone()))).derive(var)
             }
@@ -252,7 +512,7 @@ fn test_derive() {
         f3d!("xyz^2").gradient(),
         vec![f3d!("yz^2"), f3d!("xz^2"), f3d!("2xyz")]
     );
-    // assert_eq!(f1d!("x/(x+1)").derive(1), f1d!("1/(x+1)^2"));
+    assert_eq!(f1d!("x/(x+1)").derive(1).cancel(), f1d!("1/(x+1)^2"));
     assert_eq!(f1d!("1/(3e*x^2)").derive(1), f1d!("-2/(3e*x^3)"));
     assert_eq!(f1d!("cos(x)").derive(1), f1d!("-sin(x)"));
     assert_eq!(f1d!("sin(x)").derive(1), f1d!("cos(x)"));
@@ -272,6 +532,8 @@ fn test_derive() {
     assert_eq!(f1d!("acosh(x)").derive(1), f1d!("1/(x^2-1)^(1/2)"));
     assert_eq!(f1d!("atanh(x)").derive(1), f1d!("1/(1-x^2)"));
     assert_eq!(f1d!("abs(x)").derive(1), f1d!("x/abs(x)"));
+    assert_eq!(f1d!("0.5x^2").derive(1), f1d!("1.0x"));
+    assert_eq!(f1d!("x^2.5").derive(1), f1d!("2.5x^1.5"));
 
     // F2D
     assert_eq!(f2d!("xy+y^2").gradient(), vec![f2d!("y"), f2d!("x+2y")]);
@@ -295,17 +557,68 @@ fn test_derive() {
     );
 
     // FND
-    let v = ['w', 'f'];
+    let v = ["w", "f"];
     assert_eq!(
         fnd!("w+f^2", &v).gradient(),
         vec![fnd!("1", &v), fnd!("2f", &v)]
     );
 
+    let v = ["rho", "v_x"];
+    assert_eq!(
+        fnd!("rho v_x^2", &v).gradient(),
+        vec![fnd!("v_x^2", &v), fnd!("2rho v_x", &v)]
+    );
+
+    let v = ["w", "f"];
     assert_eq!(
         fnd!("w+f^2", &v).hessian(),
         vec![
             vec![fnd!("0", &v), fnd!("0", &v)],
             vec![fnd!("0", &v), fnd!("2", &v)]
         ]
-    )
+    );
+
+    // Vector calculus
+    assert_eq!(f2d!("e^x sin(y)").laplacian(), f2d!("0"));
+    assert_eq!(f3d!("1/x").laplacian(), f3d!("2/x^3"));
+    assert_eq!(
+        f3d!("x^2+yz").directional_derivative(&[1., 0.5, 0.]),
+        f3d!("2x+0.5z")
+    );
+    assert_eq!(
+        fnd!("rho^2 v_x", &["rho", "v_x"]).directional_derivative(&[0., 3.]),
+        fnd!("3rho^2", &["rho", "v_x"])
+    );
+    let field = [f3d!("x^2y"), f3d!("sin(z)"), f3d!("xyz")];
+    assert_eq!(divergence(&field), f3d!("2xy+xy"));
+    assert_eq!(curl(&field), [f3d!("xz-cos(z)"), f3d!("-yz"), f3d!("-x^2")]);
+    // The curl of a gradient vanishes, the divergence of the curl too
+    let f = f3d!("x^2y^3+zsin(x)+e^y");
+    let gradient: [F3D; 3] = f.gradient().try_into().unwrap();
+    assert_eq!(curl(&gradient), [f3d!("0"), f3d!("0"), f3d!("0")]);
+    assert_eq!(divergence(&curl(&field)), f3d!("0"));
+
+    // Mixed partials
+    let f = f3d!("x^3y^2sin(z)+e^(xz)");
+    assert_eq!(
+        f.derive_multi(&[('x', 2), ('y', 1)]),
+        f.derive('x', 1).derive('x', 1).derive('y', 1)
+    );
+    let partials = f.partials(3);
+    // 1 + 3 + 6 + 10
+    assert_eq!(partials.len(), 20);
+    for (index, der) in partials.iter() {
+        let orders = [('x', index[0]), ('y', index[1]), ('z', index[2])];
+        assert_eq!(*der, f.derive_multi(&orders));
+    }
+    assert_eq!(partials.get(&[1, 1, 2]), None);
+    assert_eq!(
+        partials.iter().map(|(index, _)| index.iter().sum()).max(),
+        Some(3)
+    );
+    let vars = ["a", "b", "c", "d"];
+    let f = fnd!("a b^2 c^3 d", &vars);
+    assert_eq!(f.partials(2).len(), 15);
+    assert_eq!(f.partials(0).get(&[0, 0, 0, 0]), Some(&f));
+    assert_eq!(f.derive_multi(&[("b", 2), ("c", 1)]), fnd!("6ac^2d", &vars));
 }
@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use num_traits::{Signed, ToPrimitive};
+
 use crate::{FType, Func, F1D, F2D, F3D};
 
 impl Display for Func {
@@ -7,43 +9,21 @@ impl Display for Func {
         match self {
             Self::E => write!(f, "e"),
             Self::PI => write!(f, "\u{1D70B}"),
-            Self::Var(char) => write!(f, "{}", char),
+            Self::Var(name) => write!(f, "{}", name),
             Self::Num(num) => write!(f, "{}", num),
+            Self::Float(num) => write!(f, "{:?}", num),
+            Self::Rational(num) => write!(f, "{}", num),
             Self::Param(par, _) => write!(f, "[{}]", par),
-            Self::S(kind, arg) => match kind {
-                FType::Sin => write!(f, "sin({})", arg),
-                FType::Cos => write!(f, "cos({})", arg),
-                FType::Tan => write!(f, "tan({})", arg),
-                FType::Cot => write!(f, "cot({})", arg),
-                FType::Sec => write!(f, "sec({})", arg),
-                FType::Csc => write!(f, "csc({})", arg),
-                FType::ASin => write!(f, "asin({})", arg),
-                FType::ACos => write!(f, "acos({})", arg),
-                FType::ATan => write!(f, "atan({})", arg),
-                FType::Sinh => write!(f, "sinh({})", arg),
-                FType::Cosh => write!(f, "cosh({})", arg),
-                FType::Tanh => write!(f, "tanh({})", arg),
-                FType::Coth => write!(f, "coth({})", arg),
-                FType::Sech => write!(f, "sech({})", arg),
-                FType::Csch => write!(f, "csch({})", arg),
-                FType::ASinh => write!(f, "asinh({})", arg),
-                FType::ACosh => write!(f, "acosh({})", arg),
-                FType::ATanh => write!(f, "atanh({})", arg),
-                FType::Abs => write!(f, "|{}|", arg),
-                FType::Ln => write!(f, "ln({})", arg),
-            },
+            Self::S(FType::Abs, arg) => write!(f, "|{}|", arg),
+            Self::S(kind, arg) => write!(f, "{}({})", kind, arg),
             Self::Add(add) => {
                 let mut output = String::from("");
                 for (i, el) in add.iter().enumerate() {
                     if i != 0 {
                         if let Func::Mul(mul) = el {
-                            if !mul.is_empty() {
-                                if let Func::Num(val) = mul[0] {
-                                    if val < 0 {
-                                        output += &format!("{}", el);
-                                        continue;
-                                    }
-                                }
+                            if !mul.is_empty() && is_negative(&mul[0]) {
+                                output += &format!("{}", el);
+                                continue;
                             }
                         }
                         output += &format!("+{}", el);
@@ -54,57 +34,95 @@ impl Display for Func {
                 write!(f, "{}", output)
             }
             Self::Mul(mul) => {
-                let mut output = String::from("");
-                let mut found_div = false;
-                let mut has_divs = false;
+                let mut num = String::new();
+                let mut den: Vec<String> = Vec::new();
+                let mut prev_var: Option<&str> = None;
 
-                for (i, el) in mul.iter().enumerate() {
-                    if let Func::Num(val) = el {
-                        if *val == -1 {
-                            output += "-";
+                for el in mul {
+                    match el {
+                        Func::Num(-1) => {
+                            num += "-";
                             continue;
                         }
-                    }
-                    if let Func::Pow(base, exp) = el {
-                        if let Func::Num(e) = **exp {
-                            if e < 0 {
-                                if !found_div {
-                                    output += "/";
-                                    if i != mul.len() - 1 {
-                                        has_divs = true;
-                                        output += "(";
+                        // 3/7 -> 3 on top, 7 at the bottom
+                        Func::Rational(val) => {
+                            match val.numer().to_i32() {
+                                Some(1) => (),
+                                Some(-1) => num += "-",
+                                _ => num += &val.numer().to_string(),
+                            }
+                            if !val.is_integer() {
+                                den.push(val.denom().to_string());
+                            }
+                            prev_var = None;
+                            continue;
+                        }
+                        Func::Pow(base, exp) => {
+                            if let Func::Num(e) = **exp {
+                                if e < 0 {
+                                    let mut div = match **base {
+                                        Func::Add(_) | Func::Mul(_) => format!("({})", base),
+                                        _ => format!("{}", base),
+                                    };
+                                    if e != -1 {
+                                        div += &format!("^{}", e.unsigned_abs());
                                     }
+                                    den.push(div);
+                                    continue;
                                 }
-
-                                match **base {
-                                    Func::Add(_) | Func::Mul(_) => output += &format!("({})", base),
-                                    _ => output += &format!("{}", base),
-                                };
-                                if e != -1 {
-                                    output += &format!("^{}", -e);
-                                }
-
-                                found_div = true;
-                                continue;
                             }
                         }
+                        _ => (),
+                    }
+                    // Keeps "theta*x" from reading as a single identifier
+                    if let (Some(prev), Func::Var(name)) = (prev_var, el) {
+                        if prev.len() > 1 || name.len() > 1 {
+                            num += "*";
+                        }
+                    }
+                    prev_var = match el {
+                        Func::Var(name) => Some(name),
+                        _ => None,
+                    };
+                    let factor = match el {
+                        Func::Add(_) => format!("({})", el),
+                        _ => format!("{}", el),
+                    };
+                    // Keeps "x*0.5^y" from reading as "x0.5^y"
+                    if !num.is_empty()
+                        && !num.ends_with('-')
+                        && factor.starts_with(char::is_numeric)
+                    {
+                        num += "*";
                     }
-                    output += &format!("{}", el);
+                    num += &factor;
                 }
-                if has_divs {
-                    output += ")";
+                if num.is_empty() || num == "-" {
+                    num += "1";
                 }
-                write!(f, "{}", output)
+                match den.len() {
+                    0 => write!(f, "{}", num),
+                    1 => write!(f, "{}/{}", num, den[0]),
+                    _ => write!(f, "{}/({})", num, den.concat()),
+                }
+            }
+            // x^-2 -> 1/x^2, like in products
+            Func::Pow(_, exp) if matches!(**exp, Func::Num(e) if e < 0) => {
+                write!(f, "{}", Func::Mul(vec![self.clone()]))
             }
             Func::Pow(base, exp) => {
                 let mut output = String::new();
 
                 match **base {
-                    Func::Add(_) | Func::Mul(_) => output += &format!("({})^", base),
+                    Func::Add(_) | Func::Mul(_) | Func::Pow(..) | Func::Rational(_) => {
+                        output += &format!("({})^", base)
+                    }
                     _ => output += &format!("{}^", base),
                 };
                 match **exp {
                     Func::Add(_) | Func::Mul(_) => output += &format!("({})", exp),
+                    Func::Float(val) if val < 0. => output += &format!("({})", exp),
+                    Func::Rational(_) => output += &format!("({})", exp),
                     _ => output += &format!("{}", exp),
                 };
 
@@ -114,6 +132,34 @@ impl Display for Func {
     }
 }
 
+impl Display for FType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FType::Sin => "sin",
+            FType::Cos => "cos",
+            FType::Tan => "tan",
+            FType::Cot => "cot",
+            FType::Sec => "sec",
+            FType::Csc => "csc",
+            FType::ASin => "asin",
+            FType::ACos => "acos",
+            FType::ATan => "atan",
+            FType::Sinh => "sinh",
+            FType::Cosh => "cosh",
+            FType::Tanh => "tanh",
+            FType::Coth => "coth",
+            FType::Sech => "sech",
+            FType::Csch => "csch",
+            FType::ASinh => "asinh",
+            FType::ACosh => "acosh",
+            FType::ATanh => "atanh",
+            FType::Abs => "abs",
+            FType::Ln => "ln",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl Display for F1D {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -133,10 +179,20 @@ impl Display for F3D {
 impl Func {
     pub(crate) fn latex(&self) -> String {
         match self {
-            Func::Var(char) => char.to_string(),
+            Func::Var(name) => var_latex(name),
             Func::PI => String::from(r"\pi"),
             Func::E => String::from("e"),
             Func::Num(val) => format!("{}", val),
+            Func::Float(val) => float_latex(*val),
+            Func::Rational(val) if val.is_integer() => format!("{}", val),
+            Func::Rational(val) => {
+                let frac = format!(r"\frac{{{}}}{{{}}}", val.numer().abs(), val.denom());
+                if val.is_negative() {
+                    format!("-{}", frac)
+                } else {
+                    frac
+                }
+            }
             Func::Param(par, _) => format!(r"\text{{{par}}}"),
             Func::Add(add) => {
                 let mut output = String::from("");
@@ -144,13 +200,9 @@ impl Func {
                 for (i, el) in add.iter().enumerate() {
                     if i != 0 {
                         if let Func::Mul(mul) = el {
-                            if !mul.is_empty() {
-                                if let Func::Num(val) = mul[0] {
-                                    if val < 0 {
-                                        output += &format!("{}", el);
-                                        continue;
-                                    }
-                                }
+                            if !mul.is_empty() && is_negative(&mul[0]) {
+                                output += &el.latex();
+                                continue;
                             }
                         }
                         output += &format!("+{}", el.latex());
@@ -161,41 +213,59 @@ impl Func {
                 output
             }
             Func::Mul(mul) => {
-                let mut output = String::from("");
-                let mut found_div = false;
+                let mut num = String::new();
+                let mut den = String::new();
 
                 for el in mul {
-                    if let Func::Pow(base, exp) = el {
-                        if **exp < 0 {
-                            if !found_div {
-                                output = format!(r"\frac{{{}}}{{", output);
+                    match el {
+                        Func::Num(-1) => {
+                            num += "-";
+                            continue;
+                        }
+                        Func::Rational(val) => {
+                            match val.numer().to_i32() {
+                                Some(1) => (),
+                                Some(-1) => num += "-",
+                                _ => num += &val.numer().to_string(),
+                            }
+                            if !val.is_integer() {
+                                den += &val.denom().to_string();
                             }
-                            found_div = true;
+                            continue;
+                        }
+                        Func::Pow(base, exp) if **exp < 0 => {
                             let div = Func::Pow(base.clone(), Box::new(-1 * *exp.clone()));
-                            output += &div.latex();
+                            push_latex_factor(&mut den, &div.latex());
                             continue;
                         }
+                        _ => (),
                     }
 
-                    output += &el.latex();
+                    push_latex_factor(&mut num, &el.latex());
                 }
 
-                if found_div {
-                    output += "}";
+                if den.is_empty() {
+                    return num;
+                }
+                if num.is_empty() || num == "-" {
+                    num += "1";
+                }
+                match num.strip_prefix('-') {
+                    Some(num) => format!(r"-\frac{{{}}}{{{}}}", num, den),
+                    None => format!(r"\frac{{{}}}{{{}}}", num, den),
                 }
-                output
             }
             Func::Pow(base, exp) => {
                 let mut output = String::new();
 
                 match **base {
-                    Func::Add(_) | Func::Mul(_) | Func::Pow(..) => {
+                    Func::Add(_) | Func::Mul(_) | Func::Pow(..) | Func::Rational(_) => {
                         output += &format!("({})", base.latex())
                     }
                     _ => output += &base.latex(),
                 };
                 match **exp {
-                    Func::Add(_) | Func::Mul(_) | Func::Pow(..) => {
+                    Func::Add(_) | Func::Mul(_) | Func::Pow(..) | Func::Rational(_) => {
                         output += &format!("^{{{}}}", exp.latex())
                     }
                     Func::Num(1) => (),
@@ -204,32 +274,56 @@ impl Func {
 
                 output
             }
-            Func::S(kind, arg) => match kind {
-                FType::Ln => format!("ln({})", arg.latex()),
-                FType::Sin => format!("sin({})", arg.latex()),
-                FType::Cos => format!("cos({})", arg.latex()),
-                FType::Tan => format!("tan({})", arg.latex()),
-                FType::Cot => format!("cot({})", arg.latex()),
-                FType::Sec => format!("sec({})", arg.latex()),
-                FType::Csc => format!("csc({})", arg.latex()),
-                FType::ACos => format!("acos({})", arg.latex()),
-                FType::ASin => format!("asin({})", arg.latex()),
-                FType::ATan => format!("atan({})", arg.latex()),
-                FType::Sinh => format!("sinh({})", arg.latex()),
-                FType::Cosh => format!("cosh({})", arg.latex()),
-                FType::Tanh => format!("tanh({})", arg.latex()),
-                FType::Coth => format!("coth({})", arg.latex()),
-                FType::Sech => format!("sech({})", arg.latex()),
-                FType::Csch => format!("csch({})", arg.latex()),
-                FType::ASinh => format!("asinh({})", arg.latex()),
-                FType::ACosh => format!("acosh({})", arg.latex()),
-                FType::ATanh => format!("atanh({})", arg.latex()),
-                FType::Abs => format!("|{}|", arg.latex()),
-            },
+            Func::S(FType::Abs, arg) => format!("|{}|", arg.latex()),
+            Func::S(kind, arg) => format!("{}({})", kind, arg.latex()),
         }
     }
 }
 
+const GREEK: [&str; 24] = [
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa",
+    "lambda", "mu", "nu", "xi", "omicron", "rho", "sigma", "tau", "upsilon", "phi", "chi", "psi",
+    "omega", "varphi",
+];
+
+// theta -> \theta, v_x -> v_x
+fn var_latex(name: &str) -> String {
+    if GREEK.contains(&name) {
+        format!(r"\{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// A command like \theta needs a space before a following letter
+fn push_latex_factor(output: &mut String, factor: &str) {
+    let command = output
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .ends_with('\\');
+    if command && factor.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        output.push(' ');
+    }
+    *output += factor;
+}
+
+fn is_negative(func: &Func) -> bool {
+    match func {
+        Func::Num(val) => *val < 0,
+        Func::Float(val) => *val < 0.,
+        Func::Rational(val) => val.is_negative(),
+        _ => false,
+    }
+}
+
+// 6.022e23 -> 6.022 \cdot 10^{23}
+fn float_latex(val: f64) -> String {
+    let repr = format!("{:?}", val);
+    match repr.split_once('e') {
+        Some((mantissa, exp)) => format!(r"{} \cdot 10^{{{}}}", mantissa, exp),
+        None => repr,
+    }
+}
+
 #[test]
 fn test_display() {
     use crate::{f1d, f2d, f3d};
@@ -250,6 +344,7 @@ fn test_display() {
     assert_eq!(format!("{}", f2d!("x+(-5y)")), "x-5y");
     assert_eq!(format!("{}", f2d!("-xy")), "-xy");
     assert_eq!(format!("{}", f2d!("x/(x+y)^2")), "x/(x+y)^2");
+    assert_eq!(format!("{}", f2d!("1/(x+y)^2+1/x")), "1/x+1/(x+y)^2");
     assert_eq!(format!("{}", f2d!("(x+y)^(e+2)")), "(x+y)^(2+e)");
     assert_eq!(
         format!(
@@ -261,6 +356,18 @@ fn test_display() {
         "|x|+asin(x)+atan(x)+tanh(x)+coth(x)+sech(x)+csch(x)+asinh(x)+acosh(x)+atanh(x)"
     );
     assert_eq!(format!("{}", f3d!("xyz")), "xyz");
+    assert_eq!(format!("{}", f1d!("x-0.5x^2")), "x-0.5x^2");
+    assert_eq!(format!("{}", f1d!("6.022e23x^(-0.5)")), "6.022e23x^(-0.5)");
+}
+
+#[test]
+fn test_display_multichar() {
+    use crate::{fnd, FND};
+    let vars = ["theta", "x", "v_x"];
+    let f = fnd!("theta x+2v_x", &vars);
+    assert_eq!(format!("{}", f.func), "theta*x+2v_x");
+    assert_eq!(fnd!("theta*x+2v_x", &vars), f);
+    assert_eq!(f.func.latex(), r"\theta x+2v_x");
 }
 
 #[test]
@@ -280,6 +387,7 @@ fn test_latex() {
     assert_eq!(f3d!("(x+2)^3").latex(), "(2+x)^3");
     assert_eq!(f3d!("(x+2)^(3+y)").latex(), "(2+x)^{3+y}");
     assert_eq!(f3d!("x^(3+y)").latex(), "x^{3+y}");
+    assert_eq!(f1d!("1.5e-7x").latex(), r"1.5 \cdot 10^{-7}x");
     assert_eq!(f3d!("-x+[eta]").latex(), r"\text{eta}-x");
     assert_eq!(
         format!(
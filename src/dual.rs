@@ -0,0 +1,343 @@
+use crate::{compile::has_vars, FType, Func, F1D, F2D, F3D, FND};
+
+// Value, gradient and (for second order) hessian of a sub-expression at a point
+struct Jet {
+    val: f64,
+    grad: Vec<f64>,
+    // Row-major n x n, empty for first order
+    hess: Vec<f64>,
+}
+
+impl Jet {
+    fn constant(val: f64, vars: usize, second: bool) -> Self {
+        Jet {
+            val,
+            grad: vec![0.; vars],
+            hess: if second {
+                vec![0.; vars * vars]
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    fn var(val: f64, slot: usize, vars: usize, second: bool) -> Self {
+        let mut jet = Jet::constant(val, vars, second);
+        jet.grad[slot] = 1.;
+        jet
+    }
+
+    fn add(mut self, rhs: &Jet) -> Self {
+        self.val += rhs.val;
+        for (grad, rhs) in self.grad.iter_mut().zip(&rhs.grad) {
+            *grad += rhs;
+        }
+        for (hess, rhs) in self.hess.iter_mut().zip(&rhs.hess) {
+            *hess += rhs;
+        }
+        self
+    }
+
+    fn mul(self, rhs: &Jet) -> Self {
+        let n = self.grad.len();
+        let mut hess = Vec::with_capacity(self.hess.len());
+        for i in 0..self.hess.len() {
+            let (row, col) = (i / n, i % n);
+            hess.push(
+                self.val * rhs.hess[i]
+                    + rhs.val * self.hess[i]
+                    + self.grad[row] * rhs.grad[col]
+                    + rhs.grad[row] * self.grad[col],
+            );
+        }
+        Jet {
+            val: self.val * rhs.val,
+            grad: self
+                .grad
+                .iter()
+                .zip(&rhs.grad)
+                .map(|(grad, rhs_grad)| self.val * rhs_grad + rhs.val * grad)
+                .collect(),
+            hess,
+        }
+    }
+
+    // f(self) given f, f' and f'' at self.val
+    fn chain(self, val: f64, d1: f64, d2: f64) -> Self {
+        let n = self.grad.len();
+        let hess = (0..self.hess.len())
+            .map(|i| d1 * self.hess[i] + d2 * self.grad[i / n] * self.grad[i % n])
+            .collect();
+        Jet {
+            val,
+            grad: self.grad.iter().map(|grad| d1 * grad).collect(),
+            hess,
+        }
+    }
+
+    fn apply(self, kind: FType) -> Self {
+        let val = kind.apply(self.val);
+        let (d1, d2) = kind.derivatives(self.val, val);
+        self.chain(val, d1, d2)
+    }
+}
+
+impl FType {
+    // First and second derivative at x, val is the function at x
+    pub(crate) fn derivatives(&self, x: f64, val: f64) -> (f64, f64) {
+        match self {
+            FType::Sin => (x.cos(), -val),
+            FType::Cos => (-x.sin(), -val),
+            FType::Tan => (1. + val * val, 2. * val * (1. + val * val)),
+            FType::Cot => (-(1. + val * val), 2. * val * (1. + val * val)),
+            FType::Sec => {
+                let tan = x.tan();
+                (val * tan, val * (tan * tan + val * val))
+            }
+            FType::Csc => {
+                let cot = 1. / x.tan();
+                (-val * cot, val * (cot * cot + val * val))
+            }
+            FType::ASin => {
+                let den = 1. - x * x;
+                (1. / den.sqrt(), x / (den * den.sqrt()))
+            }
+            FType::ACos => {
+                let den = 1. - x * x;
+                (-1. / den.sqrt(), -x / (den * den.sqrt()))
+            }
+            FType::ATan => {
+                let den = 1. + x * x;
+                (1. / den, -2. * x / (den * den))
+            }
+            FType::Sinh => (x.cosh(), val),
+            FType::Cosh => (x.sinh(), val),
+            FType::Tanh => (1. - val * val, -2. * val * (1. - val * val)),
+            FType::Coth => (1. - val * val, -2. * val * (1. - val * val)),
+            FType::Sech => {
+                let tanh = x.tanh();
+                (-val * tanh, val * (tanh * tanh - val * val))
+            }
+            FType::Csch => {
+                let coth = 1. / x.tanh();
+                (-val * coth, val * (coth * coth + val * val))
+            }
+            FType::ASinh => {
+                let den = x * x + 1.;
+                (1. / den.sqrt(), -x / (den * den.sqrt()))
+            }
+            FType::ACosh => {
+                let den = x * x - 1.;
+                (1. / den.sqrt(), -x / (den * den.sqrt()))
+            }
+            FType::ATanh => {
+                let den = 1. - x * x;
+                (1. / den, 2. * x / (den * den))
+            }
+            // x/|x| as in derive: NaN at 0, where abs has no derivative
+            FType::Abs => {
+                let sign = x / x.abs();
+                (sign, 0. * sign)
+            }
+            FType::Ln => (1. / x, -1. / (x * x)),
+        }
+    }
+}
+
+impl Func {
+    fn jet(&self, point: &[(&str, f64)], vars: &[&str], second: bool) -> Jet {
+        let n = vars.len();
+        if !has_vars(self) {
+            return Jet::constant(self.eval(point), n, second);
+        }
+        match self {
+            Func::Var(name) => {
+                let val = point
+                    .iter()
+                    .find(|&x| x.0 == name)
+                    .unwrap_or_else(|| panic!("{} has no value", name))
+                    .1;
+                match vars.iter().position(|var| var == name) {
+                    Some(slot) => Jet::var(val, slot, n, second),
+                    None => Jet::constant(val, n, second),
+                }
+            }
+            Func::Add(add) => add.iter().fold(Jet::constant(0., n, second), |acc, term| {
+                acc.add(&term.jet(point, vars, second))
+            }),
+            Func::Mul(mul) => mul.iter().fold(Jet::constant(1., n, second), |acc, term| {
+                acc.mul(&term.jet(point, vars, second))
+            }),
+            Func::Pow(base, exp) if !has_vars(exp) => {
+                // x^c
+                let exp = exp.eval(point);
+                let base = base.jet(point, vars, second);
+                let x = base.val;
+                base.chain(
+                    x.powf(exp),
+                    exp * x.powf(exp - 1.),
+                    exp * (exp - 1.) * x.powf(exp - 2.),
+                )
+            }
+            Func::Pow(base, exp) if !has_vars(base) => {
+                // c^y
+                let ln = base.eval(point).ln();
+                let exp = exp.jet(point, vars, second);
+                let val = self.eval(point);
+                exp.chain(val, ln * val, ln * ln * val)
+            }
+            Func::Pow(base, exp) => {
+                // e^(y ln(x))
+                let ln = base.jet(point, vars, second).apply(FType::Ln);
+                let exponent = exp.jet(point, vars, second).mul(&ln);
+                let val = self.eval(point);
+                exponent.chain(val, val, val)
+            }
+            Func::S(kind, arg) => arg.jet(point, vars, second).apply(*kind),
+            _ => unreachable!(),
+        }
+    }
+
+    // Value, gradient and hessian in one pass
+    fn eval_hessian(&self, point: &[(&str, f64)], vars: &[&str]) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+        let jet = self.jet(point, vars, true);
+        let hess = jet
+            .hess
+            .chunks(vars.len().max(1))
+            .map(|row| row.to_vec())
+            .collect();
+        (jet.val, jet.grad, hess)
+    }
+}
+
+impl F1D {
+    /// Evaluates f(x) and f'(x) in one pass, without building the derivative
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("x^3").eval_with_derivative(2.), (8., 12.));
+    /// ```
+    pub fn eval_with_derivative(&self, x: f64) -> (f64, f64) {
+        let jet = self.0.jet(&[("x", x)], &["x"], false);
+        (jet.val, jet.grad[0])
+    }
+    /// Evaluates f(x), f'(x) and f''(x) in one pass
+    pub fn eval_with_second_derivative(&self, x: f64) -> (f64, f64, f64) {
+        let jet = self.0.jet(&[("x", x)], &["x"], true);
+        (jet.val, jet.grad[0], jet.hess[0])
+    }
+}
+impl F2D {
+    /// Evaluates the function and its gradient at (x,y) in one pass
+    pub fn eval_with_gradient(&self, x: f64, y: f64) -> (f64, Vec<f64>) {
+        let jet = self.0.jet(&[("x", x), ("y", y)], &["x", "y"], false);
+        (jet.val, jet.grad)
+    }
+    /// Evaluates the function, its gradient and its hessian at (x,y) in one pass
+    pub fn eval_with_hessian(&self, x: f64, y: f64) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+        self.0.eval_hessian(&[("x", x), ("y", y)], &["x", "y"])
+    }
+}
+impl F3D {
+    /// Evaluates the function and its gradient at (x,y,z) in one pass
+    /// ```
+    /// use ruut_functions::{f3d, F3D};
+    ///
+    /// let (val, grad) = f3d!("xy+z^2").eval_with_gradient(1., 2., 3.);
+    /// assert_eq!(val, 11.);
+    /// assert_eq!(grad, vec![2., 1., 6.]);
+    /// ```
+    pub fn eval_with_gradient(&self, x: f64, y: f64, z: f64) -> (f64, Vec<f64>) {
+        let point = [("x", x), ("y", y), ("z", z)];
+        let jet = self.0.jet(&point, &["x", "y", "z"], false);
+        (jet.val, jet.grad)
+    }
+    /// Evaluates the function, its gradient and its hessian at (x,y,z) in one pass
+    pub fn eval_with_hessian(&self, x: f64, y: f64, z: f64) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+        let point = [("x", x), ("y", y), ("z", z)];
+        self.0.eval_hessian(&point, &["x", "y", "z"])
+    }
+}
+impl FND {
+    /// Evaluates the function, its gradient and its hessian in one pass
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("a^2b", &["a", "b"]);
+    /// let (val, grad, hessian) = f.eval_with_hessian(&[("a", 3.), ("b", 2.)]);
+    /// assert_eq!((val, grad), (18., vec![12., 9.]));
+    /// assert_eq!(hessian, vec![vec![4., 6.], vec![6., 0.]]);
+    /// ```
+    ///
+    /// # Panics
+    /// If a variable of the function has no value
+    pub fn eval_with_hessian(&self, val: &[(&str, f64)]) -> (f64, Vec<f64>, Vec<Vec<f64>>) {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        self.func.eval_hessian(val, &vars)
+    }
+}
+
+#[test]
+fn test_dual() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * (1. + b.abs());
+
+    let funcs = [
+        f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)cos(14x)+3/2-1/7"),
+        f1d!("e^(-x/2)+sqrt(x)+asin(x/4)acos(x/5)+atan(x)+abs(x-3)"),
+        f1d!("sinh(x)cosh(x)tanh(x)+coth(x)-sech(x)+csch(x)+asinh(x)+acosh(x+1)+atanh(x/3)"),
+        f1d!("cot(x)+sec(x)^2-csc(3x)+x^x+2^x+x^(-3)+pi^e"),
+    ];
+    for func in &funcs {
+        let (d1, d2) = (func.derive(1), func.derive(2));
+        for x in [0.1, 0.5, 1.2, 2.7] {
+            let (val, der, der2) = func.eval_with_second_derivative(x);
+            assert!(close(val, func.eval(x)), "{} at {}", func, x);
+            assert!(close(der, d1.eval(x)), "{} at {}", func, x);
+            assert!(close(der2, d2.eval(x)), "{} at {}", func, x);
+            assert_eq!(func.eval_with_derivative(x), (val, der));
+        }
+    }
+
+    // No derivative at the corner of abs, like the symbolic one
+    let f = f1d!("abs(x)+x");
+    assert!(f.eval_with_derivative(0.).1.is_nan());
+    assert!(f.eval_with_second_derivative(0.).2.is_nan());
+    assert!(f.derive(1).eval(0.).is_nan());
+    assert_eq!(f.eval_with_derivative(-2.), (0., 0.));
+    let (_, grad) = fnd!("abs(a)b", &["a", "b"]).eval_with_gradient(&[("a", 0.), ("b", 1.)]);
+    assert!(grad[0].is_nan());
+
+    let f = f2d!("x^y+sin(xy)/y");
+    let (grad, hessian) = (f.gradient(), f.hessian());
+    let (val, g, h) = f.eval_with_hessian(1.3, 0.7);
+    assert!(close(val, f.eval(1.3, 0.7)));
+    for i in 0..2 {
+        assert!(close(g[i], grad[i].eval(1.3, 0.7)));
+        for j in 0..2 {
+            assert!(close(h[i][j], hessian[i][j].eval(1.3, 0.7)));
+        }
+    }
+    assert_eq!(f.eval_with_gradient(1.3, 0.7), (val, g));
+
+    let f = f3d!("xyz+e^(x-z)ln(y)+[a]x");
+    let (grad, hessian) = (f.gradient(), f.hessian());
+    let (_, g, h) = f.eval_with_hessian(0.4, 2.1, -0.3);
+    for i in 0..3 {
+        assert!(close(g[i], grad[i].eval(0.4, 2.1, -0.3)));
+        for j in 0..3 {
+            assert!(close(h[i][j], hessian[i][j].eval(0.4, 2.1, -0.3)));
+        }
+    }
+
+    let vars = ["theta", "r", "v_x"];
+    let f = fnd!("r^2cos(theta)+v_x/r", &vars);
+    let point = [("theta", 0.3), ("r", 1.7), ("v_x", -2.)];
+    let grad = f.gradient();
+    let (val, g) = f.eval_with_gradient(&point);
+    assert!(close(val, f.eval(&point)));
+    for i in 0..3 {
+        assert!(close(g[i], grad[i].eval(&point)));
+    }
+}
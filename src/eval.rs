@@ -1,67 +1,212 @@
+use num_traits::ToPrimitive;
+
 use crate::{FType, Func, F1D, F2D, F3D, FND};
-use std::f64;
+use std::{f64, fmt::Display};
+
+#[derive(Debug, PartialEq, Clone)]
+/// Error returned by `try_eval`
+/// ```
+/// use ruut_functions::{f1d, EvalError, FType, Operation, F1D};
+///
+/// let err = f1d!("x+ln(x-2)").try_eval(1.).unwrap_err();
+/// assert_eq!(
+///     err,
+///     EvalError::Domain {
+///         expr: "ln(-2+x)".to_string(),
+///         op: Operation::Func(FType::Ln),
+///         arg: -1.
+///     }
+/// );
+/// assert_eq!(format!("{}", err), "ln(-1) is not finite in `ln(-2+x)`");
+/// ```
+pub enum EvalError {
+    /// An operation got an argument outside of its domain (or overflowed)
+    Domain {
+        /// Innermost sub-expression that failed
+        expr: String,
+        /// Operation it applies
+        op: Operation,
+        /// Argument of the operation, the base for powers
+        arg: f64,
+    },
+    /// A variable was not given a value
+    MissingVariable(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Operation that failed during evaluation
+pub enum Operation {
+    /// Elementary function
+    Func(FType),
+    /// Power with the given exponent
+    Pow(f64),
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::Domain { expr, op, arg } => match op {
+                Operation::Func(kind) => {
+                    write!(f, "{}({}) is not finite in `{}`", kind, arg, expr)
+                }
+                Operation::Pow(exp) => write!(f, "{}^{} is not finite in `{}`", arg, exp, expr),
+            },
+            EvalError::MissingVariable(var) => write!(f, "missing value for variable `{}`", var),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
 
 impl F1D {
     /// Evaluates function at x
     pub fn eval(&self, x: f64) -> f64 {
-        self.0.eval(&[('x', x)])
+        self.0.eval(&[("x", x)])
+    }
+    /// Evaluates function at x, failing on the first operation that is out of its domain
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("asin(x)").try_eval(0.), Ok(0.));
+    /// assert!(f1d!("asin(x)").try_eval(2.).is_err());
+    /// assert!(f1d!("1/x").try_eval(0.).is_err());
+    /// ```
+    pub fn try_eval(&self, x: f64) -> Result<f64, EvalError> {
+        self.0.try_eval(&[("x", x)])
     }
 }
 impl F2D {
     /// Evaluates function at (x,y)
     pub fn eval(&self, x: f64, y: f64) -> f64 {
-        self.0.eval(&[('x', x), ('y', y)])
+        self.0.eval(&[("x", x), ("y", y)])
+    }
+    /// Evaluates function at (x,y), failing on the first operation that is out of its domain
+    pub fn try_eval(&self, x: f64, y: f64) -> Result<f64, EvalError> {
+        self.0.try_eval(&[("x", x), ("y", y)])
     }
 }
 impl F3D {
     /// Evaluates function at (x,y,z)
     pub fn eval(&self, x: f64, y: f64, z: f64) -> f64 {
-        self.0.eval(&[('x', x), ('y', y), ('z', z)])
+        self.0.eval(&[("x", x), ("y", y), ("z", z)])
+    }
+    /// Evaluates function at (x,y,z), failing on the first operation that is out of its domain
+    pub fn try_eval(&self, x: f64, y: f64, z: f64) -> Result<f64, EvalError> {
+        self.0.try_eval(&[("x", x), ("y", y), ("z", z)])
     }
 }
 impl FND {
-    /// Evaluates function
-    pub fn eval(&self, val: &[(char, f64)]) -> f64 {
+    /// Evaluates function, `try_eval` reports a missing variable instead
+    ///
+    /// # Panics
+    /// If a variable of the function has no value
+    pub fn eval(&self, val: &[(&str, f64)]) -> f64 {
         self.func.eval(val)
     }
+    /// Evaluates function, failing on a missing variable or on the first operation that is out
+    /// of its domain
+    /// ```
+    /// use ruut_functions::{fnd, EvalError, FND};
+    ///
+    /// let f = fnd!("a/b", &["a", "b"]);
+    /// assert_eq!(f.try_eval(&[("a", 1.), ("b", 4.)]), Ok(0.25));
+    /// assert_eq!(
+    ///     f.try_eval(&[("a", 1.)]),
+    ///     Err(EvalError::MissingVariable("b".to_string()))
+    /// );
+    /// ```
+    pub fn try_eval(&self, val: &[(&str, f64)]) -> Result<f64, EvalError> {
+        self.func.try_eval(val)
+    }
 }
 
 impl Func {
-    fn eval(&self, val: &[(char, f64)]) -> f64 {
+    pub(crate) fn eval(&self, val: &[(&str, f64)]) -> f64 {
         match &self {
-            Func::Var(var) => val.iter().find(|&x| x.0 == *var).unwrap().1,
+            Func::Var(var) => {
+                val.iter()
+                    .find(|&x| x.0 == var)
+                    .unwrap_or_else(|| panic!("{} has no value", var))
+                    .1
+            }
             Func::Num(val) => *val as f64,
+            Func::Float(val) => *val,
+            Func::Rational(val) => val.to_f64().unwrap_or(f64::NAN),
             Func::E => std::f64::consts::E,
             Func::PI => std::f64::consts::PI,
             Func::Param(_, v) => *v,
             Func::Add(add) => add.iter().map(|term| term.eval(val)).sum::<f64>(),
             Func::Mul(mul) => mul.iter().map(|term| term.eval(val)).product::<f64>(),
             Func::Pow(base, exp) => base.eval(val).powf(exp.eval(val)),
+            Func::S(kind, arg) => kind.apply(arg.eval(val)),
+        }
+    }
+}
+
+impl Func {
+    // Same operations as eval, checking the result of every pow and function
+    fn try_eval(&self, val: &[(&str, f64)]) -> Result<f64, EvalError> {
+        match self {
+            Func::Var(var) => val
+                .iter()
+                .find(|&x| x.0 == var)
+                .map(|x| x.1)
+                .ok_or_else(|| EvalError::MissingVariable(var.clone())),
+            Func::Add(add) => add.iter().map(|term| term.try_eval(val)).sum(),
+            Func::Mul(mul) => mul.iter().map(|term| term.try_eval(val)).product(),
+            Func::Pow(base, exp) => {
+                let base = base.try_eval(val)?;
+                let exp = exp.try_eval(val)?;
+                let result = base.powf(exp);
+                if base.is_finite() && exp.is_finite() && !result.is_finite() {
+                    return Err(EvalError::Domain {
+                        expr: self.to_string(),
+                        op: Operation::Pow(exp),
+                        arg: base,
+                    });
+                }
+                Ok(result)
+            }
             Func::S(kind, arg) => {
-                let arg = arg.eval(val);
-                match kind {
-                    FType::Ln => arg.ln(),
-                    FType::Sin => arg.sin(),
-                    FType::Cos => arg.cos(),
-                    FType::Tan => arg.tan(),
-                    FType::Cot => 1. / arg.tan(),
-                    FType::Sec => 1. / arg.cos(),
-                    FType::Csc => 1. / arg.sin(),
-                    FType::ASin => arg.asin(),
-                    FType::ACos => arg.acos(),
-                    FType::ATan => arg.atan(),
-                    FType::Sinh => arg.sinh(),
-                    FType::Cosh => arg.cosh(),
-                    FType::Tanh => arg.tanh(),
-                    FType::Coth => 1. / arg.tanh(),
-                    FType::Sech => 1. / arg.cosh(),
-                    FType::Csch => 1. / arg.sinh(),
-                    FType::ASinh => arg.asinh(),
-                    FType::ACosh => arg.acosh(),
-                    FType::ATanh => arg.atanh(),
-                    FType::Abs => arg.abs(),
+                let arg = arg.try_eval(val)?;
+                let result = kind.apply(arg);
+                if arg.is_finite() && !result.is_finite() {
+                    return Err(EvalError::Domain {
+                        expr: self.to_string(),
+                        op: Operation::Func(*kind),
+                        arg,
+                    });
                 }
+                Ok(result)
             }
+            _ => Ok(self.eval(val)),
+        }
+    }
+}
+
+impl FType {
+    pub(crate) fn apply(&self, arg: f64) -> f64 {
+        match self {
+            FType::Ln => arg.ln(),
+            FType::Sin => arg.sin(),
+            FType::Cos => arg.cos(),
+            FType::Tan => arg.tan(),
+            FType::Cot => 1. / arg.tan(),
+            FType::Sec => 1. / arg.cos(),
+            FType::Csc => 1. / arg.sin(),
+            FType::ASin => arg.asin(),
+            FType::ACos => arg.acos(),
+            FType::ATan => arg.atan(),
+            FType::Sinh => arg.sinh(),
+            FType::Cosh => arg.cosh(),
+            FType::Tanh => arg.tanh(),
+            FType::Coth => 1. / arg.tanh(),
+            FType::Sech => 1. / arg.cosh(),
+            FType::Csch => 1. / arg.sinh(),
+            FType::ASinh => arg.asinh(),
+            FType::ACosh => arg.acosh(),
+            FType::ATanh => arg.atanh(),
+            FType::Abs => arg.abs(),
         }
     }
 }
@@ -95,25 +240,23 @@ fn test_eval() {
     use crate::{f1d, f2d, f3d, fnd};
     assert_eq!(f1d!("(2/3)-(1/3)x").eval(1.), 0.3333333333333333);
     assert_eq!(f1d!("1/x").eval(0.), f64::INFINITY);
+    assert_eq!(f1d!("0.5x^2").eval(3.), 4.5);
+    assert!((f1d!("1e-3*sin(x)").eval(1.) - 1e-3 * 1f64.sin()).abs() < 1e-15);
     assert_eq!(f2d!("xy+sin(x)").eval(3., 5.), 15.141120008059866);
     assert_eq!(f3d!("xyz*e*pi+1-x").eval(3., 5., 7.), 894.6720933807245);
     assert_eq!(
-        fnd!("f+g^2", &['f', 'g']).eval(&[('f', 6.), ('g', 7.)]),
+        fnd!("f+g^2", &["f", "g"]).eval(&[("f", 6.), ("g", 7.)]),
         55.
     );
 
     // vecs
     assert_eq!(
-        eval_vec_f1d(&vec![f1d!("cos(x)"), f1d!("tan(x)sec(x)")], 2.),
+        eval_vec_f1d(&[f1d!("cos(x)"), f1d!("tan(x)sec(x)")], 2.),
         vec![-0.4161468365471424, 5.25064633769958]
     );
 
     assert_eq!(
-        eval_vec_f2d(
-            &vec![f2d!("cot(xy)+csc(y)"), f2d!("asin(y)+acos(y)")],
-            2.,
-            1.
-        ),
+        eval_vec_f2d(&[f2d!("cot(xy)+csc(y)"), f2d!("asin(y)+acos(y)")], 2., 1.),
         vec![0.7307375514178355, f64::consts::FRAC_PI_2]
     );
 
@@ -127,6 +270,61 @@ fn test_eval() {
             0.5,
             0.2
         ),
-        vec![2.788677381770281, 9.59735546963752]
+        vec![2.788677381770281, 9.597355469637519]
+    );
+}
+
+#[test]
+#[should_panic(expected = "g has no value")]
+fn test_eval_missing_var() {
+    use crate::fnd;
+
+    fnd!("f+g", &["f", "g"]).eval(&[("f", 1.)]);
+}
+
+#[test]
+fn test_try_eval() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    assert_eq!(f1d!("ln(x)").try_eval(1.), Ok(0.));
+    assert_eq!(
+        f1d!("2+ln(x)").try_eval(-1.),
+        Err(EvalError::Domain {
+            expr: "ln(x)".to_string(),
+            op: Operation::Func(FType::Ln),
+            arg: -1.
+        })
+    );
+    assert_eq!(
+        f1d!("sin(1/x)").try_eval(0.),
+        Err(EvalError::Domain {
+            expr: "1/x".to_string(),
+            op: Operation::Pow(-1.),
+            arg: 0.
+        })
+    );
+    assert_eq!(
+        f2d!("acosh(xy)").try_eval(0.5, 1.),
+        Err(EvalError::Domain {
+            expr: "acosh(xy)".to_string(),
+            op: Operation::Func(FType::ACosh),
+            arg: 0.5
+        })
+    );
+    assert_eq!(
+        format!("{}", f3d!("x+(y-z)^0.5").try_eval(0., 1., 2.).unwrap_err()),
+        "-1^0.5 is not finite in `(y-z)^0.5`"
+    );
+    assert_eq!(
+        f3d!("xyz*e*pi+1-x").try_eval(3., 5., 7.),
+        Ok(f3d!("xyz*e*pi+1-x").eval(3., 5., 7.))
+    );
+    assert_eq!(
+        fnd!("f+g^2", &["f", "g"]).try_eval(&[("g", 7.)]),
+        Err(EvalError::MissingVariable("f".to_string()))
+    );
+    assert_eq!(
+        format!("{}", EvalError::MissingVariable("f".to_string())),
+        "missing value for variable `f`"
     );
 }
@@ -0,0 +1,138 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::{
+    simp::{from_rational, simp_node},
+    Func, F1D, F2D, F3D, FND,
+};
+
+impl Func {
+    // Distributes products and integer powers over sums, like terms are then collected by simp
+    pub(crate) fn expand(&self) -> Self {
+        let mut terms = self.terms();
+        let mut result = match terms.len() {
+            1 => terms.remove(0),
+            _ => Func::Add(terms),
+        };
+        simp_node(&mut result);
+        result
+    }
+
+    // Terms of the expanded sum
+    fn terms(&self) -> Vec<Self> {
+        match self {
+            Func::Add(add) => add.iter().flat_map(|term| term.terms()).collect(),
+            Func::Mul(mul) => mul.iter().fold(vec![Func::Num(1)], |acc, factor| {
+                let factor = factor.terms();
+                acc.iter()
+                    .flat_map(|lhs| factor.iter().map(|rhs| lhs.clone() * rhs.clone()))
+                    .collect()
+            }),
+            Func::Pow(base, exp) => match **exp {
+                Func::Num(n) if n > 1 => match base.terms().as_slice() {
+                    [term] => vec![power(term, n)],
+                    terms => multinomial(terms, n),
+                },
+                _ => vec![base.expand().pow(exp.expand())],
+            },
+            Func::S(kind, arg) => vec![Func::S(*kind, Box::new(arg.expand()))],
+            _ => vec![self.clone()],
+        }
+    }
+}
+
+// (ab)^n = a^n b^n
+fn power(term: &Func, n: i32) -> Func {
+    match term {
+        Func::Mul(mul) => mul.iter().map(|factor| factor.clone().powi(n)).product(),
+        _ => term.clone().powi(n),
+    }
+}
+
+// (t1+...+tk)^n = sum of n!/(a1!...ak!) t1^a1...tk^ak over a1+...+ak = n
+fn multinomial(terms: &[Func], n: i32) -> Vec<Func> {
+    let Some((first, rest)) = terms.split_first() else {
+        return vec![];
+    };
+    if rest.is_empty() {
+        return vec![power(first, n)];
+    }
+    let mut result = Vec::new();
+    let mut binomial = BigInt::from(1);
+    for k in 0..=n {
+        let coef = from_rational(BigRational::from_integer(binomial.clone())) * power(first, k);
+        for term in multinomial(rest, n - k) {
+            result.push(coef.clone() * term);
+        }
+        binomial = binomial * (n - k) / (k + 1);
+    }
+    result
+}
+
+impl F1D {
+    /// Expands products and integer powers of sums, collecting like terms
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("(x+1)^2-x^2-2x-1").expand(), f1d!("0"));
+    /// assert_eq!(f1d!("(x+2)(x-3)").expand(), f1d!("x^2-x-6"));
+    /// ```
+    pub fn expand(&self) -> Self {
+        F1D(self.0.expand())
+    }
+}
+impl F2D {
+    /// Expands products and integer powers of sums, collecting like terms
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// assert_eq!(f2d!("(x+y)^2-x^2-y^2").expand(), f2d!("2xy"));
+    /// ```
+    pub fn expand(&self) -> Self {
+        F2D(self.0.expand())
+    }
+}
+impl F3D {
+    /// Expands products and integer powers of sums, collecting like terms
+    pub fn expand(&self) -> Self {
+        F3D(self.0.expand())
+    }
+}
+impl FND {
+    /// Expands products and integer powers of sums, collecting like terms
+    pub fn expand(&self) -> Self {
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.expand(),
+        }
+    }
+}
+
+#[test]
+fn test_expand() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    assert_eq!(f1d!("(x+1)^3").expand(), f1d!("x^3+3x^2+3x+1"));
+    assert_eq!(f1d!("(2x-1)^2+4x").expand(), f1d!("4x^2+1"));
+    assert_eq!(f1d!("x(x+1)(x-1)").expand(), f1d!("x^3-x"));
+    assert_eq!(f1d!("(x/2+1/3)^2").expand(), f1d!("x^2/4+x/3+1/9"));
+    // Inside functions too, denominators are kept
+    assert_eq!(f1d!("sin((x+1)^2)").expand(), f1d!("sin(x^2+2x+1)"));
+    assert_eq!(f1d!("(x+1)^2/x").expand(), f1d!("x+2+1/x"));
+    assert_eq!(f1d!("(x+1)^(1/2)").expand(), f1d!("(x+1)^(1/2)"));
+
+    assert_eq!(f2d!("(x+y)(x-y)-(x-y)^2").expand(), f2d!("-2y^2+2xy"));
+    // Terms of the same rank may come in any order
+    let diff = f3d!("(x+y+z)^2").expand().0 - f3d!("x^2+y^2+z^2+2xy+2xz+2yz").0;
+    assert_eq!(diff.expand(), 0);
+    let vars = ["a", "b"];
+    assert_eq!(
+        fnd!("(a+b)^4-(a-b)^4", &vars).expand(),
+        fnd!("8ab^3+8a^3b", &vars)
+    );
+
+    // Derivatives of expansions agree with expansions of derivatives
+    let f = f1d!("(x^2+x+1)^5");
+    let diff = f.expand().derive(1).0 - f.derive(1).expand().0;
+    assert_eq!(diff.expand(), 0);
+}
@@ -0,0 +1,286 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::{
+    poly::Poly,
+    simp::{as_rational, from_rational},
+    Func, F1D, F2D, F3D, FND,
+};
+
+// Rational coefficient and (base, exponent) factors of a term
+type Monomial = (BigRational, Vec<(Func, i32)>);
+
+impl Func {
+    // Factors the sums in the tree: content, common factors, perfect squares and univariate
+    // polynomials over the rationals
+    pub(crate) fn factor(&self, vars: &[&str]) -> Self {
+        match self {
+            Func::Add(add) => factor_sum(add, vars)
+                .unwrap_or_else(|| add.iter().map(|term| term.factor(vars)).sum()),
+            Func::Mul(mul) => mul.iter().map(|factor| factor.factor(vars)).product(),
+            Func::Pow(base, exp) => base.factor(vars).pow(*exp.clone()),
+            Func::S(kind, arg) => Func::S(*kind, Box::new(arg.factor(vars))),
+            _ => self.clone(),
+        }
+    }
+}
+
+fn factor_sum(add: &[Func], vars: &[&str]) -> Option<Func> {
+    let terms: Vec<Monomial> = add.iter().map(monomial).collect();
+    let (content, common, rest) = pull_common(&terms);
+    let sum = Func::Add(rest.iter().map(from_monomial).collect());
+
+    let factored = vars
+        .iter()
+        .find_map(|var| {
+            let poly = Poly::from_func(&sum, var).filter(|poly| poly.degree() > 1)?;
+            factor_poly(&poly, var)
+        })
+        .or_else(|| perfect_square(&rest));
+    if content.is_one() && common.is_empty() && factored.is_none() {
+        return None;
+    }
+
+    let mut result = from_rational(content);
+    for (base, exp) in common {
+        result *= base.powi(exp);
+    }
+    result *= factored.unwrap_or_else(|| {
+        rest.iter()
+            .map(|term| from_monomial(term).factor(vars))
+            .sum()
+    });
+    Some(result)
+}
+
+// Content times the product of the factors of the square-free parts, None when irreducible
+fn factor_poly(poly: &Poly, var: &str) -> Option<Func> {
+    let (content, part) = poly.primitive();
+    let mut factors = Vec::new();
+    for (square_free, mult) in part.square_free() {
+        for factor in square_free.primitive().1.factor_square_free() {
+            factors.push((factor, mult));
+        }
+    }
+    if let [(_, 1)] = factors.as_slice() {
+        return None;
+    }
+    let mut result = from_rational(content);
+    for (factor, mult) in factors {
+        result *= factor.to_func(var).powi(mult as i32);
+    }
+    Some(result)
+}
+
+fn monomial(term: &Func) -> Monomial {
+    let factors = match term {
+        Func::Mul(mul) => mul.as_slice(),
+        _ => std::slice::from_ref(term),
+    };
+    let mut coef = BigRational::one();
+    let mut powers = Vec::new();
+    for factor in factors {
+        match (as_rational(factor), factor) {
+            (Some(val), _) => coef *= val,
+            (None, Func::Pow(base, exp)) if matches!(**exp, Func::Num(_)) => {
+                let Func::Num(exp) = **exp else {
+                    unreachable!()
+                };
+                powers.push((*base.clone(), exp));
+            }
+            (None, _) => powers.push((factor.clone(), 1)),
+        }
+    }
+    (coef, powers)
+}
+
+fn from_monomial((coef, powers): &Monomial) -> Func {
+    powers
+        .iter()
+        .fold(from_rational(coef.clone()), |acc, (base, exp)| {
+            acc * base.clone().powi(*exp)
+        })
+}
+
+// Content (gcd of the numerators over lcm of the denominators, negative when every
+// coefficient is), factors every term shares and the terms divided by them
+fn pull_common(terms: &[Monomial]) -> (BigRational, Vec<(Func, i32)>, Vec<Monomial>) {
+    let numer = terms
+        .iter()
+        .fold(BigInt::zero(), |acc, (coef, _)| acc.gcd(coef.numer()));
+    let denom = terms
+        .iter()
+        .fold(BigInt::one(), |acc, (coef, _)| acc.lcm(coef.denom()));
+    let mut content = match numer.is_zero() {
+        true => BigRational::one(),
+        false => BigRational::new(numer, denom),
+    };
+    if terms.iter().all(|(coef, _)| coef.is_negative()) {
+        content = -content;
+    }
+
+    let mut common = Vec::new();
+    if let Some(((_, first), others)) = terms.split_first() {
+        for (base, _) in first {
+            let exps: Vec<i32> = terms
+                .iter()
+                .filter_map(|(_, powers)| {
+                    powers.iter().find(|(b, _)| b == base).map(|(_, exp)| *exp)
+                })
+                .collect();
+            if exps.len() != others.len() + 1 || common.iter().any(|(b, _)| b == base) {
+                continue;
+            }
+            let exp = match (exps.iter().min(), exps.iter().max()) {
+                (Some(&min), _) if min > 0 => min,
+                (_, Some(&max)) if max < 0 => max,
+                _ => continue,
+            };
+            common.push((base.clone(), exp));
+        }
+    }
+
+    let rest = terms
+        .iter()
+        .map(|(coef, powers)| {
+            let powers = powers
+                .iter()
+                .filter_map(|(base, exp)| {
+                    let shared = common
+                        .iter()
+                        .find(|(b, _)| b == base)
+                        .map_or(0, |(_, exp)| *exp);
+                    (exp - shared != 0).then(|| (base.clone(), exp - shared))
+                })
+                .collect();
+            (coef / &content, powers)
+        })
+        .collect();
+    (content, common, rest)
+}
+
+// a^2+2ab+b^2 = (a+b)^2 and a^2-2ab+b^2 = (a-b)^2 for monomials a and b
+fn perfect_square(terms: &[Monomial]) -> Option<Func> {
+    let [t1, t2, t3] = terms else {
+        return None;
+    };
+    for (sq1, sq2, cross) in [(t1, t2, t3), (t1, t3, t2), (t2, t3, t1)] {
+        let (Some(a), Some(b)) = (square_root(sq1), square_root(sq2)) else {
+            continue;
+        };
+        let double = 2 * a.clone() * b.clone();
+        let cross = from_monomial(cross);
+        if cross == double {
+            return Some((a + b).powi(2));
+        }
+        if cross == -1 * double {
+            return Some((a - b).powi(2));
+        }
+    }
+    None
+}
+
+fn square_root((coef, powers): &Monomial) -> Option<Func> {
+    if coef.is_negative() || powers.iter().any(|(_, exp)| exp % 2 != 0) {
+        return None;
+    }
+    let (numer, denom) = (coef.numer().sqrt(), coef.denom().sqrt());
+    if &(&numer * &numer) != coef.numer() || &(&denom * &denom) != coef.denom() {
+        return None;
+    }
+    let halved = powers
+        .iter()
+        .map(|(base, exp)| (base.clone(), exp / 2))
+        .collect();
+    Some(from_monomial(&(BigRational::new(numer, denom), halved)))
+}
+
+impl F1D {
+    /// Factors the polynomials in the function: content, common factors and, over the
+    /// rationals, their linear and quadratic factors. Polynomials of degree above 64 are left
+    /// as they are
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("x^2+2x+1").factor(), f1d!("(x+1)^2"));
+    /// assert_eq!(format!("{}", f1d!("2x^3-8x").factor()), "2x(-2+x)(2+x)");
+    /// ```
+    pub fn factor(&self) -> Self {
+        F1D(self.0.factor(&["x"]))
+    }
+}
+impl F2D {
+    /// Factors the polynomials in the function: content, common factors, perfect squares and
+    /// univariate polynomials over the rationals
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// assert_eq!(f2d!("x^2y+xy^2").factor(), f2d!("xy(x+y)"));
+    /// ```
+    pub fn factor(&self) -> Self {
+        F2D(self.0.factor(&["x", "y"]))
+    }
+}
+impl F3D {
+    /// Factors the polynomials in the function: content, common factors, perfect squares and
+    /// univariate polynomials over the rationals
+    pub fn factor(&self) -> Self {
+        F3D(self.0.factor(&["x", "y", "z"]))
+    }
+}
+impl FND {
+    /// Factors the polynomials in the function: content, common factors, perfect squares and
+    /// univariate polynomials over the rationals
+    pub fn factor(&self) -> Self {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.factor(&vars),
+        }
+    }
+}
+
+#[test]
+fn test_factor() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    let cases = [
+        ("x^2-1", "(-1+x)(1+x)"),
+        ("6x^2+12x+6", "6(1+x)^2"),
+        ("x^3-3x+2", "(2+x)(-1+x)^2"),
+        ("x^2/4-1/9", "(-2+3x)(2+3x)/36"),
+        ("x^4+5x^2+6", "(2+x^2)(3+x^2)"),
+        ("2x^5-x^4+2x^3-x^2", "(-1+2x)(1+x^2)x^2"),
+        ("x^2+1", "1+x^2"),
+        ("sin(x^2-4)+ln(4x^2+4x+1)", "ln((1+2x)^2)+sin((-2+x)(2+x))"),
+    ];
+    for (input, factored) in cases {
+        let f = F1D::new(input).unwrap();
+        assert_eq!(format!("{}", f.factor()), factored, "{}", input);
+        // Same function
+        for x in [-1.3, 0.4, 2.1] {
+            assert!((f.factor().eval(x) - f.eval(x)).abs() < 1e-9);
+        }
+    }
+    assert_eq!(f1d!("x^2-2").factor(), f1d!("x^2-2"));
+    // Past the degree cap
+    assert_eq!(f1d!("x^400-1").factor(), f1d!("x^400-1"));
+    assert_eq!(f1d!("x^1000000+1").factor(), f1d!("x^1000000+1"));
+    assert_eq!(
+        format!("{}", f1d!("x^64-1").factor()).split(")(").count(),
+        4
+    );
+    // Too many candidates to try, the filters reject the others cheaply
+    assert_eq!(
+        f1d!("x^64+963761198400x+963761198400").factor(),
+        f1d!("x^64+963761198400x+963761198400")
+    );
+
+    assert_eq!(f2d!("x^2+2xy+y^2").factor(), f2d!("(x+y)^2"));
+    assert_eq!(f2d!("4x^2y-4xy^2+y^3").factor(), f2d!("y(2x-y)^2"));
+    assert_eq!(f3d!("xz+yz").factor(), f3d!("z(x+y)"));
+    let vars = ["a", "b"];
+    assert_eq!(fnd!("a^3b-ab", &vars).factor(), fnd!("ab(a-1)(a+1)", &vars));
+}
@@ -0,0 +1,409 @@
+use std::collections::BinaryHeap;
+
+use crate::F1D;
+
+// Kronrod nodes on [0,1], the odd ones are the Gauss nodes
+const XGK: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.,
+];
+const WGK: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const WG: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+// Deepest recursion of adaptive Simpson
+const MAX_DEPTH: usize = 50;
+
+// Halvings over which the error of the worst segment must shrink by STALL_DECAY: around
+// 1/x it doesn't shrink at all, around x^-p for p < 1 it shrinks by 2^(p-1) per halving
+const STALL_WINDOW: u32 = 16;
+const STALL_DECAY: f64 = 0.85;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Settings of adaptive integration: it stops once the estimated error is below
+/// `abs_tol` or `rel_tol` times the value, or after `max_subdivisions`
+pub struct Quadrature {
+    /// Absolute tolerance
+    pub abs_tol: f64,
+    /// Relative tolerance
+    pub rel_tol: f64,
+    /// Most intervals that get subdivided
+    pub max_subdivisions: usize,
+}
+
+impl Default for Quadrature {
+    fn default() -> Self {
+        Quadrature {
+            abs_tol: 1e-10,
+            rel_tol: 1e-10,
+            max_subdivisions: 1000,
+        }
+    }
+}
+
+impl Quadrature {
+    pub(crate) fn tolerance(&self, value: f64) -> f64 {
+        self.abs_tol.max(self.rel_tol * value.abs())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Value of an integral with its estimated error
+pub struct Integral {
+    /// Value of the integral
+    pub value: f64,
+    /// Estimated absolute error, infinite if a singularity couldn't be resolved
+    pub error: f64,
+}
+
+// Interval of G7K15 integration, ordered by error
+struct Segment {
+    a: f64,
+    b: f64,
+    value: f64,
+    error: f64,
+    // Error of the ancestor the current window of halvings started from, and their number
+    start: f64,
+    halvings: u32,
+    // Not worth splitting further
+    singular: bool,
+}
+
+impl PartialEq for Segment {
+    fn eq(&self, other: &Self) -> bool {
+        self.error.total_cmp(&other.error).is_eq()
+    }
+}
+impl Eq for Segment {}
+impl PartialOrd for Segment {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Segment {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.error.total_cmp(&other.error)
+    }
+}
+
+impl Segment {
+    fn new(f: &impl Fn(f64) -> f64, a: f64, b: f64) -> Self {
+        let (value, error) = gauss_kronrod(f, a, b);
+        Segment {
+            a,
+            b,
+            value,
+            error,
+            start: error,
+            halvings: 0,
+            singular: false,
+        }
+    }
+
+    // Half of the parent, singular when the window ends without the error having shrunk: a
+    // singularity that isn't integrable or can't be resolved
+    fn half(f: &impl Fn(f64) -> f64, a: f64, b: f64, parent: &Segment) -> Self {
+        let mut half = Segment::new(f, a, b);
+        if parent.halvings + 1 < STALL_WINDOW {
+            (half.start, half.halvings) = (parent.start, parent.halvings + 1);
+        } else {
+            half.singular = half.error >= STALL_DECAY * parent.start
+                && half.error > 100. * f64::EPSILON * half.value.abs();
+        }
+        half
+    }
+
+    // Too narrow to be split in floating point
+    fn is_atomic(&self) -> bool {
+        let mid = (self.a + self.b) / 2.;
+        mid <= self.a || mid >= self.b
+    }
+}
+
+// Value at x, a NaN or infinity is replaced by the mean of the finite neighbours at
+// distance h when the function levels off there (a removable singularity or a jump), NaN
+// otherwise: poles like 1/x must not be averaged away
+pub(crate) fn sample(f: &impl Fn(f64) -> f64, x: f64, h: f64) -> f64 {
+    let val = f(x);
+    if val.is_finite() {
+        return val;
+    }
+    let mut sides = Vec::with_capacity(2);
+    for dir in [-1., 1.] {
+        let (near, far) = (f(x + dir * h), f(x + dir * 2. * h));
+        if !near.is_finite() {
+            continue;
+        }
+        // Growing like a pole towards x
+        if !far.is_finite() || (near - far).abs() > 1e-3 * (1. + near.abs()) {
+            return f64::NAN;
+        }
+        sides.push(near);
+    }
+    match sides.len() {
+        0 => f64::NAN,
+        len => sides.iter().sum::<f64>() / len as f64,
+    }
+}
+
+// 15 points Kronrod value and its distance from the embedded 7 points Gauss value
+pub(crate) fn gauss_kronrod(f: &impl Fn(f64) -> f64, a: f64, b: f64) -> (f64, f64) {
+    let (center, half) = ((a + b) / 2., (b - a) / 2.);
+    let h = half.abs() * 1e-7;
+    let mid = sample(f, center, h);
+    let mut kronrod = mid * WGK[7];
+    let mut gauss = mid * WG[3];
+    for i in 0..7 {
+        let dx = half * XGK[i];
+        let sum = sample(f, center - dx, h) + sample(f, center + dx, h);
+        kronrod += WGK[i] * sum;
+        if i % 2 == 1 {
+            gauss += WG[i / 2] * sum;
+        }
+    }
+    let (value, error) = (kronrod * half, ((kronrod - gauss) * half).abs());
+    if value.is_finite() {
+        (value, error)
+    } else {
+        (0., f64::INFINITY)
+    }
+}
+
+// Global adaptive G7K15: the segment with the largest error is split until the total error
+// is within tolerance
+pub(crate) fn adaptive_gk(
+    f: &impl Fn(f64) -> f64,
+    a: f64,
+    b: f64,
+    config: &Quadrature,
+) -> Integral {
+    if a > b {
+        let result = adaptive_gk(f, b, a, config);
+        return Integral {
+            value: -result.value,
+            ..result
+        };
+    }
+    let mut heap = BinaryHeap::from([Segment::new(f, a, b)]);
+    let mut done = Vec::new();
+    for _ in 0..config.max_subdivisions {
+        let value: f64 = heap.iter().chain(&done).map(|seg| seg.value).sum();
+        let error: f64 = heap.iter().chain(&done).map(|seg| seg.error).sum();
+        if error <= config.tolerance(value) {
+            break;
+        }
+        let Some(worst) = heap.pop() else {
+            break;
+        };
+        // Still the largest error at the resolution of f64: a singularity too
+        if worst.singular || worst.is_atomic() {
+            done.push(Segment {
+                error: f64::INFINITY,
+                ..worst
+            });
+            continue;
+        }
+        let mid = (worst.a + worst.b) / 2.;
+        heap.push(Segment::half(f, worst.a, mid, &worst));
+        heap.push(Segment::half(f, mid, worst.b, &worst));
+    }
+    Integral {
+        value: heap.iter().chain(&done).map(|seg| seg.value).sum(),
+        error: heap.iter().chain(&done).map(|seg| seg.error).sum(),
+    }
+}
+
+// Simpson on [a,b] given the samples at a, m, b and the whole estimate, returns value and error
+fn simpson(
+    f: &impl Fn(f64) -> f64,
+    (a, m, b): (f64, f64, f64),
+    (fa, fm, fb): (f64, f64, f64),
+    whole: f64,
+    tol: f64,
+    depth: usize,
+    budget: &mut usize,
+) -> (f64, f64) {
+    let (lm, rm) = ((a + m) / 2., (m + b) / 2.);
+    let h = (b - a).abs() * 1e-7;
+    let (flm, frm) = (sample(f, lm, h), sample(f, rm, h));
+    let left = (m - a) / 6. * (fa + 4. * flm + fm);
+    let right = (b - m) / 6. * (fm + 4. * frm + fb);
+    let delta = left + right - whole;
+    if *budget == 0 || delta.abs() <= 15. * tol {
+        return (left + right + delta / 15., delta.abs() / 15.);
+    }
+    // Out of depth or precision above tolerance: a singularity
+    if depth == 0 || lm <= a || rm >= b {
+        return (left + right + delta / 15., f64::INFINITY);
+    }
+    *budget -= 1;
+    let (left, left_err) = simpson(
+        f,
+        (a, lm, m),
+        (fa, flm, fm),
+        left,
+        tol / 2.,
+        depth - 1,
+        budget,
+    );
+    let (right, right_err) = simpson(
+        f,
+        (m, rm, b),
+        (fm, frm, fb),
+        right,
+        tol / 2.,
+        depth - 1,
+        budget,
+    );
+    (left + right, left_err + right_err)
+}
+
+impl F1D {
+    /// Integrates over [a,b] with adaptive Gauss–Kronrod (G7K15) quadrature, returning the
+    /// value and its estimated error. Interior points where the function isn't finite are
+    /// approached from their neighbours if it levels off there, the error is infinite around
+    /// singularities that aren't integrable
+    /// ```
+    /// use ruut_functions::{f1d, F1D, Quadrature};
+    ///
+    /// let result = f1d!("sin(x)/x").integrate_adaptive(-1., 1., &Quadrature::default());
+    /// assert!((result.value - 1.8921661407343662).abs() < 1e-10);
+    /// assert!(result.error < 1e-10);
+    /// ```
+    pub fn integrate_adaptive(&self, a: f64, b: f64, config: &Quadrature) -> Integral {
+        let compiled = self.compile();
+        adaptive_gk(&|x| compiled.eval(&[x]), a, b, config)
+    }
+
+    /// Integrates over [a,b] with adaptive Simpson quadrature, returning the value and its
+    /// estimated error. `max_subdivisions` bounds the number of bisections
+    /// ```
+    /// use ruut_functions::{f1d, F1D, Quadrature};
+    ///
+    /// let result = f1d!("e^x").integrate_simpson(0., 1., &Quadrature::default());
+    /// assert!((result.value - (std::f64::consts::E - 1.)).abs() < 1e-10);
+    /// ```
+    pub fn integrate_simpson(&self, a: f64, b: f64, config: &Quadrature) -> Integral {
+        if a > b {
+            let result = self.integrate_simpson(b, a, config);
+            return Integral {
+                value: -result.value,
+                ..result
+            };
+        }
+        let compiled = self.compile();
+        let f = |x: f64| compiled.eval(&[x]);
+        let m = (a + b) / 2.;
+        let h = (b - a).abs() * 1e-7;
+        let (fa, fm, fb) = (sample(&f, a, h), sample(&f, m, h), sample(&f, b, h));
+        let whole = (b - a) / 6. * (fa + 4. * fm + fb);
+        let mut budget = config.max_subdivisions;
+        let (value, error) = simpson(
+            &f,
+            (a, m, b),
+            (fa, fm, fb),
+            whole,
+            config.tolerance(whole),
+            MAX_DEPTH,
+            &mut budget,
+        );
+        if value.is_finite() {
+            Integral { value, error }
+        } else {
+            Integral {
+                value,
+                error: f64::INFINITY,
+            }
+        }
+    }
+}
+
+#[test]
+fn test_adaptive() {
+    use crate::f1d;
+    use std::f64::consts::PI;
+
+    let config = Quadrature::default();
+    let cases = [
+        (f1d!("sin(x)"), 0., PI, 2.),
+        (f1d!("x^3-2x"), -1., 3., 12.),
+        // Peaked
+        (f1d!("1/(0.0001+x^2)"), -1., 1., 200. * 100f64.atan()),
+        // Oscillatory
+        (f1d!("cos(50x)"), 0., 1., 50f64.sin() / 50.),
+        // Reversed bounds
+        (f1d!("e^x"), 1., 0., 1. - std::f64::consts::E),
+        // Removable singularity on a node
+        (f1d!("sin(x)/x"), -1., 1., 1.8921661407343662),
+    ];
+    for (f, a, b, exact) in &cases {
+        let gk = f.integrate_adaptive(*a, *b, &config);
+        assert!((gk.value - exact).abs() < 1e-9, "{} {:?}", f, gk);
+        assert!(
+            (gk.value - exact).abs() <= gk.error.max(1e-12),
+            "{} {:?}",
+            f,
+            gk
+        );
+
+        let simpson = f.integrate_simpson(
+            *a,
+            *b,
+            &Quadrature {
+                max_subdivisions: 100_000,
+                ..config
+            },
+        );
+        assert!((simpson.value - exact).abs() < 1e-8, "{} {:?}", f, simpson);
+    }
+
+    // Integrable singularity inside the interval
+    let result = f1d!("1/sqrt(abs(x))").integrate_adaptive(-1., 1., &config);
+    assert!((result.value - 4.).abs() < 1e-6);
+    let result = f1d!("abs(x)^-0.9").integrate_adaptive(-1., 1., &config);
+    assert!((result.value - 20.).abs() < 1e-6);
+
+    // Poles aren't averaged away, on a node or between nodes
+    for (f, a, b) in [
+        (f1d!("1/x"), -1., 1.),
+        (f1d!("1/(x-0.3)"), 0., 1.),
+        (f1d!("1/abs(x)"), -1., 1.),
+        (f1d!("1/x^2"), -1., 2.),
+    ] {
+        assert_eq!(
+            f.integrate_adaptive(a, b, &config).error,
+            f64::INFINITY,
+            "{}",
+            f
+        );
+        // Out of budget before reaching the pole
+        assert!(f.integrate_simpson(a, b, &config).error > 1e-3, "{}", f);
+    }
+
+    // Budget exhausted: the error says so
+    let coarse = Quadrature {
+        max_subdivisions: 3,
+        ..config
+    };
+    let result = f1d!("sin(1/x)").integrate_adaptive(0.001, 1., &coarse);
+    assert!(result.error > coarse.abs_tol);
+}
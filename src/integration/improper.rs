@@ -0,0 +1,310 @@
+use std::f64::consts::PI;
+
+use crate::{
+    integration::{Integral, Quadrature},
+    F1D,
+};
+
+// Nodes are taken for |u| <= U_MAX, where the distance from the ends, taken from the small
+// side, is down to 1e-275
+const U_MAX: f64 = 6.;
+// Halvings of the step of the tanh-sinh rule
+const MAX_LEVEL: usize = 10;
+// Outermost nodes that tell whether x·f(x) vanishes towards an end, and how fast
+const END_NODES: usize = 6;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Error of an improper integral
+pub enum IntegrationError {
+    /// The integrand doesn't vanish fast enough at an end or at infinity
+    Divergent,
+    /// The integral has no finite value
+    NotFinite,
+    /// The estimate didn't reach the tolerance: the integral converges too slowly, oscillates
+    /// at infinity or has a singularity inside the range. Holds the last estimate, with an
+    /// infinite error if the function isn't finite at an inner node
+    NotConverged(Integral),
+}
+
+impl std::fmt::Display for IntegrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrationError::Divergent => write!(f, "The integral diverges"),
+            IntegrationError::NotFinite => write!(f, "The integral has no finite value"),
+            IntegrationError::NotConverged(result) => write!(
+                f,
+                "The integral didn't converge: {} ± {}",
+                result.value, result.error
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IntegrationError {}
+
+// Tanh-sinh rule on [0,1], g gets the node t and 1-t (accurate near 1)
+fn tanh_sinh(
+    g: &impl Fn(f64, f64) -> f64,
+    config: &Quadrature,
+) -> Result<Integral, IntegrationError> {
+    // Weighted value at u, NaN where the function isn't finite
+    let term = |u: f64| {
+        let e = (PI * u.sinh()).exp();
+        let (t, s) = (e / (1. + e), 1. / (1. + e));
+        if t == 0. || s == 0. {
+            return 0.;
+        }
+        let val = g(t, s) * PI * u.cosh() * t * s;
+        if val.is_finite() {
+            val
+        } else {
+            f64::NAN
+        }
+    };
+
+    // Nodes that round onto a singular end are dropped, what lies past the last finite one is
+    // extrapolated. The weighted value over cosh(u) is x·f(x) up to a constant, with x the
+    // distance from a finite end or the distance from 0 at infinity: when it doesn't decrease
+    // towards the end, like for 1/x, the integral diverges
+    let (mut fits, mut tail) = (Vec::with_capacity(2), 0.);
+    let mut ends = [0.; 2];
+    for (side, end) in [-1., 1.].into_iter().zip(&mut ends) {
+        let outer: Vec<(f64, f64)> = (0..2 * U_MAX as usize)
+            .map(|step| side * (U_MAX - step as f64 / 2.))
+            .map(|u| (u, term(u).abs()))
+            .skip_while(|(_, val)| val.is_nan())
+            .take(END_NODES)
+            .collect();
+        let Some(&(u, val)) = outer.first() else {
+            return Err(IntegrationError::NotFinite);
+        };
+        // Up to rounding, on this half: e^x overflows past the first few nodes
+        let growing = outer
+            .windows(2)
+            .take_while(|pair| pair[1].0 * side > 0.)
+            .all(|pair| pair[0].1 / pair[0].0.cosh() >= pair[1].1 / pair[1].0.cosh() * (1. - 1e-9));
+        let nodes = outer.iter().filter(|(u, _)| u * side > 0.).count();
+        if val > 0. && nodes >= 2 && growing {
+            return Err(IntegrationError::Divergent);
+        }
+        // Past the outermost node x·f(x) ~ x^q, which leaves x·f(x)/q to the end. What lies
+        // past the half step the node covers in the sum goes in the value, and its change with q
+        // taken one node further in goes in the error
+        let rest = |q: f64| val / (PI * u.cosh() * q);
+        let slope = |i: usize| {
+            let (&(u0, val0), &(u1, val1)) = (outer.get(i)?, outer.get(i + 1)?);
+            (u1 * side > 0. && val0 > 0.).then(|| {
+                (val1 / u1.cosh() * u0.cosh() / val0).ln()
+                    / (PI * (u0.abs().sinh() - u1.abs().sinh()))
+            })
+        };
+        let (extra, q, spread) = match (slope(0), slope(1)) {
+            _ if val == 0. => (0., 0., 0.),
+            (Some(q), Some(next)) if q > 0. && next > 0. => {
+                (rest(q), q, (rest(q) - rest(next)).abs())
+            }
+            (Some(q), None) if q > 0. => (rest(q), q, rest(q)),
+            (None, _) => (0., 0., rest(1.)),
+            _ => (0., 0., f64::INFINITY),
+        };
+        *end = u.abs();
+        fits.push((u.abs(), term(u), q, extra * term(u).signum()));
+        tail += spread;
+    }
+
+    // Nodes past the outermost finite ones are extrapolated, NaNs before them are inside the
+    // range
+    let mut inner_nan = false;
+    let mut finite = |u: f64| {
+        if u.abs() > ends[usize::from(u > 0.)] {
+            return 0.;
+        }
+        let val = term(u);
+        if !val.is_nan() {
+            return val;
+        }
+        inner_nan = true;
+        0.
+    };
+
+    // x shrinks like e^(-π sinh u) towards the end, so past the half step b of the outermost
+    // node the terms go like cosh(u)·x^q. The sum of the nodes falls short of the integral up
+    // to b by h²/24 times the slope there
+    let beyond = |h: f64| -> f64 {
+        fits.iter()
+            .filter(|(.., rest)| *rest != 0.)
+            .map(|&(u, val, q, rest)| {
+                let b = u + h / 2.;
+                let decay = (-q * PI * (b.sinh() - u.sinh())).exp();
+                let slope = val * decay * b.cosh() / u.cosh() * (b.tanh() - q * PI * b.cosh());
+                rest * decay + h * h / 24. * slope
+            })
+            .sum()
+    };
+
+    let mut h = 1.;
+    let mut sum = finite(0.);
+    let mut k = 1.;
+    while k <= U_MAX {
+        sum += finite(k) + finite(-k);
+        k += 1.;
+    }
+    let mut value = h * sum + beyond(h);
+    let mut error = f64::INFINITY;
+    for level in 1..=MAX_LEVEL {
+        h /= 2.;
+        let mut u = h;
+        while u <= U_MAX {
+            sum += finite(u) + finite(-u);
+            u += 2. * h;
+        }
+        let next = h * sum + beyond(h);
+        error = (next - value).abs() + tail;
+        value = next;
+        if level >= 3 && error <= config.tolerance(value) {
+            break;
+        }
+    }
+    if !value.is_finite() {
+        return Err(IntegrationError::NotFinite);
+    }
+    if inner_nan {
+        error = f64::INFINITY;
+    }
+    let result = Integral { value, error };
+    match error <= config.tolerance(value) {
+        true => Ok(result),
+        false => Err(IntegrationError::NotConverged(result)),
+    }
+}
+
+impl F1D {
+    /// Integrates over [a,b] where a and b can be infinite and the function can be singular at
+    /// the ends, with the tanh-sinh (double exponential) rule. Infinite ranges are mapped on
+    /// [0,1] with x = a+t/(1-t). Integrands that don't vanish fast enough at the ends, like
+    /// 1/x on [0,1], are reported as divergent. When the estimate doesn't reach the tolerance,
+    /// because the integral converges slowly, oscillates or has a pole inside the range, it is
+    /// returned in `IntegrationError::NotConverged`
+    /// ```
+    /// use ruut_functions::{f1d, F1D, IntegrationError, Quadrature};
+    ///
+    /// let config = Quadrature::default();
+    /// let gauss = f1d!("e^(-x^2)").integrate_improper(0., f64::INFINITY, &config).unwrap();
+    /// assert!((gauss.value - std::f64::consts::PI.sqrt() / 2.).abs() < 1e-10);
+    ///
+    /// let singular = f1d!("1/sqrt(x)").integrate_improper(0., 1., &config).unwrap();
+    /// assert!((singular.value - 2.).abs() < 1e-10);
+    ///
+    /// let divergent = f1d!("1/x").integrate_improper(1., f64::INFINITY, &config);
+    /// assert_eq!(divergent, Err(IntegrationError::Divergent));
+    ///
+    /// let pole = f1d!("1/x").integrate_improper(-1., 1., &config);
+    /// assert!(matches!(pole, Err(IntegrationError::NotConverged(_))));
+    /// ```
+    pub fn integrate_improper(
+        &self,
+        a: f64,
+        b: f64,
+        config: &Quadrature,
+    ) -> Result<Integral, IntegrationError> {
+        if a > b {
+            let negate = |result: Integral| Integral {
+                value: -result.value,
+                ..result
+            };
+            return self
+                .integrate_improper(b, a, config)
+                .map(negate)
+                .map_err(|err| match err {
+                    IntegrationError::NotConverged(result) => {
+                        IntegrationError::NotConverged(negate(result))
+                    }
+                    err => err,
+                });
+        }
+        if a == b {
+            return Ok(Integral {
+                value: 0.,
+                error: 0.,
+            });
+        }
+        let compiled = self.compile();
+        let f = |x: f64| compiled.eval(&[x]);
+        match (a.is_finite(), b.is_finite()) {
+            (true, true) => {
+                let width = b - a;
+                // The closest end is computed from the small side, to resolve singularities
+                tanh_sinh(
+                    &|t, s| {
+                        let x = if t < 0.5 {
+                            a + width * t
+                        } else {
+                            b - width * s
+                        };
+                        f(x) * width
+                    },
+                    config,
+                )
+            }
+            (true, false) => tanh_sinh(&|t, s| f(a + t / s) / (s * s), config),
+            (false, true) => tanh_sinh(&|t, s| f(b - t / s) / (s * s), config),
+            (false, false) => tanh_sinh(&|t, s| (f(t / s) + f(-t / s)) / (s * s), config),
+        }
+    }
+}
+
+#[test]
+fn test_improper() {
+    use crate::f1d;
+    use std::f64::consts::PI;
+
+    let config = Quadrature::default();
+    let inf = f64::INFINITY;
+    let cases = [
+        (f1d!("e^(-x^2)"), -inf, inf, PI.sqrt()),
+        (f1d!("1/(1+x^2)"), -inf, 0., PI / 2.),
+        (f1d!("e^(-x)"), 2., inf, (-2f64).exp()),
+        (f1d!("1/x^2"), 1., inf, 1.),
+        (f1d!("ln(x)"), 0., 1., -1.),
+        (f1d!("x^3"), 2., -1., -3.75),
+        (f1d!("sin(x)"), 0., PI, 2.),
+        (f1d!("1/sqrt(1-x^2)"), -1., 1., PI),
+        (f1d!("x^(-0.7)"), 0., 1., 10. / 3.),
+        (f1d!("x^(-0.9)"), 0., 1., 10.),
+        (f1d!("x^(-0.99)"), 0., 1., 100.),
+        (f1d!("x^(-1.01)"), 1., inf, 100.),
+    ];
+    for (f, a, b, exact) in &cases {
+        let result = f.integrate_improper(*a, *b, &config).unwrap();
+        assert!((result.value - exact).abs() < 1e-9, "{} {:?}", f, result);
+        assert!(result.error < 1e-6, "{} {:?}", f, result);
+    }
+
+    let Err(IntegrationError::NotConverged(result)) =
+        f1d!("sin(x)/x").integrate_improper(0., inf, &config)
+    else {
+        panic!("sin(x)/x converged");
+    };
+    assert!(result.error > 1e-3);
+    // The pole is inside the range
+    let Err(IntegrationError::NotConverged(result)) =
+        f1d!("1/x").integrate_improper(-1., 1., &config)
+    else {
+        panic!("1/x converged on [-1,1]");
+    };
+    assert_eq!(result.error, inf);
+
+    for (f, a, b) in [
+        (f1d!("1/x"), 0., 1.),
+        (f1d!("1/x"), 1., inf),
+        (f1d!("1/(x-1)^2"), 1., 2.),
+        (f1d!("e^x"), -inf, inf),
+    ] {
+        assert_eq!(
+            f.integrate_improper(a, b, &config),
+            Err(IntegrationError::Divergent),
+            "{}",
+            f
+        );
+    }
+}
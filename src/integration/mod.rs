@@ -1,5 +1,13 @@
 use crate::F1D;
 
+mod adaptive;
+mod improper;
+mod multiple;
+mod symbolic;
+
+pub use adaptive::{Integral, Quadrature};
+pub use improper::IntegrationError;
+
 impl F1D {
     /// Computes the definite integral of F1D
     /// ```
@@ -0,0 +1,291 @@
+use std::cell::Cell;
+
+use crate::{
+    integration::{adaptive::adaptive_gk, Integral, Quadrature},
+    F1D, F2D, F3D, FND,
+};
+
+// Independently shifted copies of the Halton sequence, their spread estimates the error
+const SHIFTS: usize = 16;
+
+// Bounds of one variable given the outer ones
+type Limits<'a> = (
+    Box<dyn Fn(&[f64]) -> f64 + 'a>,
+    Box<dyn Fn(&[f64]) -> f64 + 'a>,
+);
+
+fn constant<'a>((a, b): (f64, f64)) -> Limits<'a> {
+    (Box::new(move |_| a), Box::new(move |_| b))
+}
+
+// Iterated adaptive G7K15 over the first variable left in limits, the error adds the worst
+// inner error times the width
+fn iterated(
+    f: &dyn Fn(&[f64]) -> f64,
+    limits: &[Limits],
+    outer: &[f64],
+    config: &Quadrature,
+) -> Integral {
+    let Some(((lo, hi), inner)) = limits.split_first() else {
+        return Integral {
+            value: f(outer),
+            error: 0.,
+        };
+    };
+    let (a, b) = (lo(outer), hi(outer));
+    let worst = Cell::new(0f64);
+    let mut point = outer.to_vec();
+    point.push(0.);
+    let result = adaptive_gk(
+        &|x| {
+            let mut point = point.clone();
+            *point.last_mut().unwrap() = x;
+            let result = iterated(f, inner, &point, config);
+            worst.set(worst.get().max(result.error));
+            result.value
+        },
+        a,
+        b,
+        config,
+    );
+    Integral {
+        value: result.value,
+        error: result.error + (b - a).abs() * worst.get(),
+    }
+}
+
+// Radical inverse of index in base, the index-th point of the Halton sequence
+fn halton(mut index: u64, base: u64) -> f64 {
+    let (mut result, mut scale) = (0., 1.);
+    while index > 0 {
+        scale /= base as f64;
+        result += (index % base) as f64 * scale;
+        index /= base;
+    }
+    result
+}
+
+fn primes(n: usize) -> Vec<u64> {
+    let mut primes = Vec::with_capacity(n);
+    let mut candidate = 2;
+    while primes.len() < n {
+        if primes.iter().all(|p| candidate % p != 0) {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+// SplitMix64 mapped on [0,1)
+fn split_mix(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (z ^ (z >> 31)) as f64 / (u64::MAX as f64 + 1.)
+}
+
+impl F2D {
+    /// Integrates over the rectangle [x.0,x.1]×[y.0,y.1] with iterated adaptive G7K15
+    /// ```
+    /// use ruut_functions::{f2d, F2D, Quadrature};
+    ///
+    /// let result = f2d!("xy").integrate_rect((0., 1.), (0., 2.), &Quadrature::default());
+    /// assert!((result.value - 1.).abs() < 1e-10);
+    /// ```
+    pub fn integrate_rect(&self, x: (f64, f64), y: (f64, f64), config: &Quadrature) -> Integral {
+        let compiled = self.compile();
+        iterated(
+            &|point| compiled.eval(point),
+            &[constant(x), constant(y)],
+            &[],
+            config,
+        )
+    }
+
+    /// Integrates over the region x.0 <= x <= x.1, y.0(x) <= y <= y.1(x)
+    /// ```
+    /// use ruut_functions::{f1d, f2d, F1D, F2D, Quadrature};
+    ///
+    /// // Area of the unit disk
+    /// let (lower, upper) = (f1d!("-sqrt(1-x^2)"), f1d!("sqrt(1-x^2)"));
+    /// let result = f2d!("1").integrate_region((-1., 1.), (&lower, &upper), &Quadrature::default());
+    /// assert!((result.value - std::f64::consts::PI).abs() < 1e-8);
+    /// ```
+    pub fn integrate_region(
+        &self,
+        x: (f64, f64),
+        y: (&F1D, &F1D),
+        config: &Quadrature,
+    ) -> Integral {
+        let compiled = self.compile();
+        let (y_lo, y_hi) = (y.0.compile(), y.1.compile());
+        iterated(
+            &|point| compiled.eval(point),
+            &[
+                constant(x),
+                (
+                    Box::new(move |outer| y_lo.eval(outer)),
+                    Box::new(move |outer| y_hi.eval(outer)),
+                ),
+            ],
+            &[],
+            config,
+        )
+    }
+}
+
+impl F3D {
+    /// Integrates over the box [x.0,x.1]×[y.0,y.1]×[z.0,z.1] with iterated adaptive G7K15
+    /// ```
+    /// use ruut_functions::{f3d, F3D, Quadrature};
+    ///
+    /// let config = Quadrature::default();
+    /// let result = f3d!("xyz").integrate_box((0., 1.), (0., 1.), (0., 2.), &config);
+    /// assert!((result.value - 0.5).abs() < 1e-10);
+    /// ```
+    pub fn integrate_box(
+        &self,
+        x: (f64, f64),
+        y: (f64, f64),
+        z: (f64, f64),
+        config: &Quadrature,
+    ) -> Integral {
+        let compiled = self.compile();
+        iterated(
+            &|point| compiled.eval(point),
+            &[constant(x), constant(y), constant(z)],
+            &[],
+            config,
+        )
+    }
+
+    /// Integrates over the region x.0 <= x <= x.1, y.0(x) <= y <= y.1(x),
+    /// z.0(x,y) <= z <= z.1(x,y)
+    pub fn integrate_region(
+        &self,
+        x: (f64, f64),
+        y: (&F1D, &F1D),
+        z: (&F2D, &F2D),
+        config: &Quadrature,
+    ) -> Integral {
+        let compiled = self.compile();
+        let (y_lo, y_hi) = (y.0.compile(), y.1.compile());
+        let (z_lo, z_hi) = (z.0.compile(), z.1.compile());
+        iterated(
+            &|point| compiled.eval(point),
+            &[
+                constant(x),
+                (
+                    Box::new(move |outer| y_lo.eval(outer)),
+                    Box::new(move |outer| y_hi.eval(outer)),
+                ),
+                (
+                    Box::new(move |outer| z_lo.eval(outer)),
+                    Box::new(move |outer| z_hi.eval(outer)),
+                ),
+            ],
+            &[],
+            config,
+        )
+    }
+}
+
+impl FND {
+    /// Integrates over a box, one (lower, upper) pair per variable in order, with quasi-Monte
+    /// Carlo: the Halton sequence is shifted at random 16 times, the mean of the estimates is
+    /// the value and their standard error the error. `points` is rounded up to a multiple of
+    /// 16, so there are at least 16 evaluations. The same seed gives the same result
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("a+b+c+d", &["a", "b", "c", "d"]);
+    /// let result = f.integrate_qmc(&[(0., 1.); 4], 4096, 42);
+    /// assert!((result.value - 2.).abs() < 1e-3);
+    /// assert_eq!(result, f.integrate_qmc(&[(0., 1.); 4], 4096, 42));
+    /// ```
+    ///
+    /// # Panics
+    /// If there isn't one pair of bounds per variable
+    pub fn integrate_qmc(&self, bounds: &[(f64, f64)], points: usize, seed: u64) -> Integral {
+        assert_eq!(
+            bounds.len(),
+            self.vars.len(),
+            "Bounds must be given for every variable"
+        );
+        let compiled = self.compile();
+        let bases = primes(bounds.len());
+        let volume: f64 = bounds.iter().map(|(a, b)| b - a).product();
+        let per_shift = points.div_ceil(SHIFTS).max(1);
+
+        let mut state = seed;
+        let mut point = vec![0.; bounds.len()];
+        let estimates: Vec<f64> = (0..SHIFTS)
+            .map(|_| {
+                let shift: Vec<f64> = bases.iter().map(|_| split_mix(&mut state)).collect();
+                let mut sum = 0.;
+                for index in 1..=per_shift as u64 {
+                    for (i, (a, b)) in bounds.iter().enumerate() {
+                        let u = (halton(index, bases[i]) + shift[i]).fract();
+                        point[i] = a + (b - a) * u;
+                    }
+                    sum += compiled.eval(&point);
+                }
+                volume * sum / per_shift as f64
+            })
+            .collect();
+
+        let value = estimates.iter().sum::<f64>() / SHIFTS as f64;
+        let variance = estimates
+            .iter()
+            .map(|est| (est - value).powi(2))
+            .sum::<f64>()
+            / (SHIFTS - 1) as f64;
+        Integral {
+            value,
+            error: (variance / SHIFTS as f64).sqrt(),
+        }
+    }
+}
+
+#[test]
+fn test_multiple() {
+    use crate::{f1d, f2d, f3d, fnd};
+    use std::f64::consts::PI;
+
+    let config = Quadrature::default();
+    let result = f2d!("sin(x)cos(y)").integrate_rect((0., PI), (0., PI / 2.), &config);
+    assert!((result.value - 2.).abs() < 1e-10);
+    assert!(result.error < 1e-8);
+    let result = f2d!("e^(x+y)").integrate_rect((1., 0.), (0., 1.), &config);
+    assert!((result.value + (std::f64::consts::E - 1.).powi(2)).abs() < 1e-10);
+
+    // Triangle under y = x
+    let result = f2d!("y").integrate_region((0., 1.), (&f1d!("0"), &f1d!("x")), &config);
+    assert!((result.value - 1. / 6.).abs() < 1e-10);
+
+    let result = f3d!("x+y+z").integrate_box((0., 1.), (0., 2.), (0., 3.), &config);
+    assert!((result.value - 18.).abs() < 1e-9);
+
+    // Volume of the unit ball
+    let (y_lo, y_hi) = (f1d!("-sqrt(1-x^2)"), f1d!("sqrt(1-x^2)"));
+    let (z_lo, z_hi) = (f2d!("-sqrt(1-x^2-y^2)"), f2d!("sqrt(1-x^2-y^2)"));
+    let coarse = Quadrature {
+        abs_tol: 1e-6,
+        rel_tol: 1e-6,
+        ..config
+    };
+    let result = f3d!("1").integrate_region((-1., 1.), (&y_lo, &y_hi), (&z_lo, &z_hi), &coarse);
+    assert!((result.value - 4. * PI / 3.).abs() < 1e-5);
+
+    let vars = ["a", "b", "c", "d", "g"];
+    let f = fnd!("a^2+b^2+c^2+d^2+g^2", &vars);
+    let bounds = [(0., 1.), (0., 1.), (0., 1.), (-1., 1.), (0., 2.)];
+    let exact = 4. * (1. + 1. / 3. + 4. / 3.);
+    let result = f.integrate_qmc(&bounds, 20_000, 7);
+    assert!((result.value - exact).abs() < 5. * result.error.max(1e-6));
+    assert!(result.error < 1e-2);
+    assert_eq!(result, f.integrate_qmc(&bounds, 20_000, 7));
+    assert_ne!(result, f.integrate_qmc(&bounds, 20_000, 8));
+}
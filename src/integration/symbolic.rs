@@ -0,0 +1,463 @@
+use num_rational::BigRational;
+use num_traits::{One, Signed};
+
+use crate::{
+    compile::has_vars,
+    simp::{as_rational, from_rational, simp_node},
+    FType, Func, F1D,
+};
+
+// Deepest chain of integrations by parts
+const MAX_DEPTH: usize = 6;
+
+// Where the derivative of a result is compared with the integrand
+const CHECK_POINTS: [f64; 8] = [0.31, 0.77, 1.29, 2.13, 3.71, -0.58, -1.67, -2.9];
+
+impl F1D {
+    /// Computes an antiderivative, None if none is found.
+    ///
+    /// Covers polynomials, powers (1/x gives ln(abs(x))), exponentials, every elementary
+    /// function, linear substitution f(ax+b), u-substitution f(g(x))g'(x) and integration
+    /// by parts. Results are checked against the integrand before being returned.
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("3x^2+cos(x)").antiderivative(), Some(f1d!("x^3+sin(x)")));
+    /// assert_eq!(f1d!("1/x").antiderivative(), Some(f1d!("ln(abs(x))")));
+    /// assert_eq!(f1d!("e^(x^2)").antiderivative(), None);
+    /// ```
+    pub fn antiderivative(&self) -> Option<Self> {
+        let mut result = self.0.antiderivative(MAX_DEPTH)?;
+        simp_node(&mut result);
+        verify(&self.0, &result).then_some(F1D(result))
+    }
+}
+
+impl Func {
+    fn antiderivative(&self, depth: usize) -> Option<Self> {
+        if !has_vars(self) {
+            return Some(self.clone() * x());
+        }
+        match self {
+            Func::Var(_) => Some(x().powi(2) / 2),
+            Func::Add(add) => add.iter().map(|term| term.antiderivative(depth)).sum(),
+            Func::Mul(mul) => {
+                let (consts, deps): (Vec<&Func>, Vec<&Func>) =
+                    mul.iter().partition(|factor| !has_vars(factor));
+                let coef: Func = consts.into_iter().cloned().product();
+                let result = match deps[..] {
+                    [single] => single.antiderivative(depth),
+                    _ => product_antiderivative(&deps, depth),
+                }?;
+                Some(coef * result)
+            }
+            Func::Pow(base, exp) => pow_antiderivative(self, base, exp),
+            Func::S(kind, arg) => {
+                let slope = linear(arg)?;
+                Some(primitive(*kind, arg) / slope)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn x() -> Func {
+    Func::Var("x".to_string())
+}
+
+fn s(kind: FType, arg: Func) -> Func {
+    Func::S(kind, Box::new(arg))
+}
+
+fn sqrt(func: Func) -> Func {
+    func.pow(from_rational(BigRational::new(1.into(), 2.into())))
+}
+
+// Slope of an argument of the form ax+b
+fn linear(arg: &Func) -> Option<Func> {
+    let slope = arg.derive_nth("x", 1);
+    (!has_vars(&slope) && slope != 0).then_some(slope)
+}
+
+// Antiderivative of kind(u) with respect to u
+fn primitive(kind: FType, u: &Func) -> Func {
+    let u = u.clone();
+    match kind {
+        FType::Sin => -1 * s(FType::Cos, u),
+        FType::Cos => s(FType::Sin, u),
+        FType::Tan => -1 * s(FType::Ln, s(FType::Abs, s(FType::Cos, u))),
+        FType::Cot => s(FType::Ln, s(FType::Abs, s(FType::Sin, u))),
+        FType::Sec => s(
+            FType::Ln,
+            s(FType::Abs, s(FType::Sec, u.clone()) + s(FType::Tan, u)),
+        ),
+        FType::Csc => {
+            -1 * s(
+                FType::Ln,
+                s(FType::Abs, s(FType::Csc, u.clone()) + s(FType::Cot, u)),
+            )
+        }
+        FType::ASin => u.clone() * s(FType::ASin, u.clone()) + sqrt(1 - u.powi(2)),
+        FType::ACos => u.clone() * s(FType::ACos, u.clone()) - sqrt(1 - u.powi(2)),
+        FType::ATan => u.clone() * s(FType::ATan, u.clone()) - s(FType::Ln, 1 + u.powi(2)) / 2,
+        FType::Sinh => s(FType::Cosh, u),
+        FType::Cosh => s(FType::Sinh, u),
+        FType::Tanh => s(FType::Ln, s(FType::Cosh, u)),
+        FType::Coth => s(FType::Ln, s(FType::Abs, s(FType::Sinh, u))),
+        FType::Sech => s(FType::ATan, s(FType::Sinh, u)),
+        FType::Csch => s(FType::Ln, s(FType::Abs, s(FType::Tanh, u / 2))),
+        FType::ASinh => u.clone() * s(FType::ASinh, u.clone()) - sqrt(1 + u.powi(2)),
+        FType::ACosh => u.clone() * s(FType::ACosh, u.clone()) - sqrt(u.powi(2) - 1),
+        FType::ATanh => u.clone() * s(FType::ATanh, u.clone()) + s(FType::Ln, 1 - u.powi(2)) / 2,
+        FType::Abs => u.clone() * s(FType::Abs, u) / 2,
+        FType::Ln => u.clone() * s(FType::Ln, u.clone()) - u,
+    }
+}
+
+// Antiderivative of kind(u)^2 with respect to u
+fn primitive_squared(kind: FType, u: &Func) -> Option<Func> {
+    let u = u.clone();
+    let double = || 2 * u.clone();
+    Some(match kind {
+        FType::Sin => u.clone() / 2 - s(FType::Sin, double()) / 4,
+        FType::Cos => u.clone() / 2 + s(FType::Sin, double()) / 4,
+        FType::Tan => s(FType::Tan, u.clone()) - u,
+        FType::Cot => -1 * s(FType::Cot, u.clone()) - u,
+        FType::Sec => s(FType::Tan, u),
+        FType::Csc => -1 * s(FType::Cot, u),
+        FType::Sinh => s(FType::Sinh, double()) / 4 - u / 2,
+        FType::Cosh => s(FType::Sinh, double()) / 4 + u / 2,
+        FType::Tanh => u.clone() - s(FType::Tanh, u),
+        FType::Coth => u.clone() - s(FType::Coth, u),
+        FType::Sech => s(FType::Tanh, u),
+        FType::Csch => -1 * s(FType::Coth, u),
+        _ => return None,
+    })
+}
+
+fn pow_antiderivative(func: &Func, base: &Func, exp: &Func) -> Option<Func> {
+    match (has_vars(base), has_vars(exp)) {
+        (true, false) => {
+            if let Some(slope) = linear(base) {
+                // (ax+b)^n
+                if *exp == -1 {
+                    return Some(s(FType::Ln, s(FType::Abs, base.clone())) / slope);
+                }
+                let exp = exp.clone() + Func::Num(1);
+                return Some(base.clone().pow(exp.clone()) / (exp * slope));
+            }
+            if let (Func::S(kind, arg), true) = (base, *exp == 2) {
+                let slope = linear(arg)?;
+                return Some(primitive_squared(*kind, arg)? / slope);
+            }
+            inverse_form(base, exp)
+        }
+        (false, true) => {
+            // c^(ax+b)
+            let slope = linear(exp)?;
+            match base {
+                Func::E => Some(func.clone() / slope),
+                _ => Some(func.clone() / (slope * s(FType::Ln, base.clone()))),
+            }
+        }
+        _ => None,
+    }
+}
+
+// (c+ku^2)^(-1) and (c+ku^2)^(-1/2), the derivatives of the inverse functions, when the
+// square roots involved are rational
+fn inverse_form(base: &Func, exp: &Func) -> Option<Func> {
+    let Func::Add(add) = base else {
+        return None;
+    };
+    let [lhs, rhs] = &add[..] else {
+        return None;
+    };
+    let (constant, square) = if has_vars(lhs) {
+        (rhs, lhs)
+    } else {
+        (lhs, rhs)
+    };
+    let c = as_rational(constant)?;
+    let (k, arg) = match square {
+        Func::Pow(arg, two) if **two == 2 => (BigRational::one(), arg),
+        Func::Mul(mul) if mul.len() == 2 => match &mul[1] {
+            Func::Pow(arg, two) if **two == 2 => (as_rational(&mul[0])?, arg),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let slope = linear(arg)?;
+    // c+ku^2 = c(1±(ru)^2)
+    let r = rational_sqrt(&(&k / &c).abs())?;
+    let minus_half = BigRational::new((-1).into(), 2.into());
+    let arg = from_rational(r.clone()) * *arg.clone();
+    if c.is_positive() && !k.is_positive() && *exp == -1 {
+        // ln|(1+v)/(1-v)|/2, atanh(v) only holds for |v|<1
+        let ratio = (1 + arg.clone()) / (1 - arg);
+        let two = from_rational(BigRational::from_integer(2.into()));
+        return Some(s(FType::Ln, s(FType::Abs, ratio)) / (two * from_rational(c * r) * slope));
+    }
+    let (kind, scale) = match (c.is_positive(), k.is_positive()) {
+        (true, true) if *exp == -1 => (FType::ATan, c.clone()),
+        (true, true) if as_rational(exp)? == minus_half => (FType::ASinh, rational_sqrt(&c)?),
+        (true, false) if as_rational(exp)? == minus_half => (FType::ASin, rational_sqrt(&c)?),
+        (false, true) if as_rational(exp)? == minus_half => (FType::ACosh, rational_sqrt(&-c)?),
+        _ => return None,
+    };
+    Some(s(kind, arg) / (from_rational(scale * r) * slope))
+}
+
+fn rational_sqrt(val: &BigRational) -> Option<BigRational> {
+    let (numer, denom) = (val.numer().sqrt(), val.denom().sqrt());
+    (&numer * &numer == *val.numer() && &denom * &denom == *val.denom())
+        .then(|| BigRational::new(numer, denom))
+}
+
+// Product of two or more factors depending on x
+fn product_antiderivative(deps: &[&Func], depth: usize) -> Option<Func> {
+    let rest = |i: usize| -> Func {
+        deps.iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, factor)| (*factor).clone())
+            .product()
+    };
+
+    // f(g(x))g'(x)
+    for (i, factor) in deps.iter().enumerate() {
+        for (inner, outer) in substitutions(factor) {
+            if inner == x() {
+                continue;
+            }
+            let Some(ratio) = constant_ratio(&rest(i), &inner.derive_nth("x", 1)) else {
+                continue;
+            };
+            if let Some(outer) = outer.antiderivative(depth) {
+                return Some(ratio * outer.substitute(&[("x", inner)]));
+            }
+        }
+    }
+
+    // Distribute over a sum
+    if let Some(i) = deps
+        .iter()
+        .position(|factor| matches!(factor, Func::Add(_)))
+    {
+        if let Func::Add(add) = deps[i] {
+            let rest = rest(i);
+            return add
+                .iter()
+                .map(|term| (term.clone() * rest.clone()).antiderivative(depth))
+                .sum();
+        }
+    }
+
+    if let Some(result) = exp_trig(deps) {
+        return Some(result);
+    }
+
+    // By parts, u is chosen with the LIATE rule
+    if depth == 0 {
+        return None;
+    }
+    let (i, _) = deps
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, factor)| liate(factor))?;
+    let u = deps[i].clone();
+    let v = rest(i).antiderivative(depth - 1)?;
+    let du_v = u.derive_nth("x", 1) * v.clone();
+    Some(u * v - du_v.antiderivative(depth - 1)?)
+}
+
+// e^(ax+b)sin(cx+d) and e^(ax+b)cos(cx+d), where parts integration comes back to the
+// integrand after two steps
+fn exp_trig(deps: &[&Func]) -> Option<Func> {
+    let [lhs, rhs] = deps else {
+        return None;
+    };
+    let (exp, trig) = match lhs {
+        Func::Pow(base, _) if **base == Func::E => (lhs, rhs),
+        _ => (rhs, lhs),
+    };
+    let (Func::Pow(base, exponent), Func::S(kind, arg)) = (exp, trig) else {
+        return None;
+    };
+    if **base != Func::E {
+        return None;
+    }
+    let (a, c) = (linear(exponent)?, linear(arg)?);
+    let (sin, cos) = (s(FType::Sin, *arg.clone()), s(FType::Cos, *arg.clone()));
+    let num = match kind {
+        FType::Sin => a.clone() * sin - c.clone() * cos,
+        FType::Cos => a.clone() * cos + c.clone() * sin,
+        _ => return None,
+    };
+    Some((*exp).clone() * num / (a.powi(2) + c.powi(2)))
+}
+
+// Ways of writing a factor as outer(inner), with x in place of inner
+fn substitutions(factor: &Func) -> Vec<(Func, Func)> {
+    let mut result = vec![(factor.clone(), x())];
+    match factor {
+        Func::S(kind, arg) => result.push((*arg.clone(), s(*kind, x()))),
+        Func::Pow(base, exp) if !has_vars(exp) => {
+            result.push((*base.clone(), Func::Pow(Box::new(x()), exp.clone())))
+        }
+        Func::Pow(base, exp) if !has_vars(base) => {
+            result.push((*exp.clone(), Func::Pow(base.clone(), Box::new(x()))))
+        }
+        _ => (),
+    }
+    result
+}
+
+// a/b when they differ by a constant factor
+fn constant_ratio(a: &Func, b: &Func) -> Option<Func> {
+    let split = |func: &Func| {
+        let factors = match func {
+            Func::Mul(mul) => mul.clone(),
+            _ => vec![func.clone()],
+        };
+        let (consts, mut deps): (Vec<Func>, Vec<Func>) =
+            factors.into_iter().partition(|factor| !has_vars(factor));
+        deps.sort();
+        (consts.into_iter().product::<Func>(), deps)
+    };
+    let (a_coef, a_deps) = split(a);
+    let (b_coef, b_deps) = split(b);
+    (a_deps == b_deps && b_coef != 0).then(|| a_coef / b_coef)
+}
+
+// Logarithmic, inverse, algebraic, trigonometric, exponential
+fn liate(factor: &Func) -> usize {
+    match factor {
+        Func::S(FType::Ln, _) => 0,
+        Func::S(
+            FType::ASin | FType::ACos | FType::ATan | FType::ASinh | FType::ACosh | FType::ATanh,
+            _,
+        ) => 1,
+        Func::Var(_) => 2,
+        Func::Pow(_, exp) if !has_vars(exp) => 2,
+        Func::S(..) => 3,
+        _ => 4,
+    }
+}
+
+// The derivative of the result must match the integrand where both are defined
+fn verify(func: &Func, result: &Func) -> bool {
+    let der = result.derive_nth("x", 1);
+    let mut checked = 0;
+    for x in CHECK_POINTS {
+        let (expected, val) = (func.eval(&[("x", x)]), der.eval(&[("x", x)]));
+        if expected.is_finite() && val.is_finite() {
+            if (expected - val).abs() > 1e-8 * (1. + expected.abs()) {
+                return false;
+            }
+            checked += 1;
+        }
+    }
+    checked >= 3
+}
+
+#[test]
+fn test_antiderivative() {
+    use crate::f1d;
+
+    assert_eq!(
+        f1d!("x^3-2x+5").antiderivative(),
+        Some(f1d!("5x-x^2+x^4/4"))
+    );
+    assert_eq!(f1d!("e^(2x)").antiderivative(), Some(f1d!("e^(2x)/2")));
+    assert_eq!(
+        f1d!("1/(3x+1)").antiderivative(),
+        Some(f1d!("ln(abs(3x+1))/3"))
+    );
+    assert_eq!(f1d!("1/(1+x^2)").antiderivative(), Some(f1d!("atan(x)")));
+
+    let funcs = [
+        // Table and linear substitution
+        "sin(2x+1)",
+        "cos(x/3)",
+        "tan(x)",
+        "cot(x)",
+        "sec(x)",
+        "csc(x)",
+        "asin(x/4)",
+        "acos(x/5)",
+        "atan(x)",
+        "sinh(x)",
+        "cosh(2x)",
+        "tanh(x)",
+        "coth(x)",
+        "sech(x)",
+        "csch(x)",
+        "asinh(x)",
+        "acosh(x+4)",
+        "atanh(x/4)",
+        "abs(x)",
+        "ln(x)",
+        "2^x",
+        "sqrt(x)",
+        "(2x+1)^(-2)",
+        "pi x^e",
+        "sin(x)^2",
+        "cos(3x)^2",
+        "sec(x)^2",
+        "tan(x)^2",
+        "1/sqrt(1-x^2/16)",
+        "1/sqrt(1+x^2)",
+        "1/(1-(x/4)^2)",
+        // u-substitution
+        "xsin(x^2)",
+        "sin(x)cos(x)",
+        "tan(x)",
+        "x/(x^2+1)",
+        "ln(x)/x",
+        "e^(sin(x))cos(x)",
+        "3x^2e^(x^3)",
+        "x^2(x^3+1)^4",
+        "sqrt(x^2+1)x",
+        "sec(x)^2e^tan(x)",
+        // By parts
+        "xe^x",
+        "x^2e^(-x)",
+        "x^3sin(x)",
+        "xcos(2x)",
+        "xln(x)",
+        "xsinh(x)",
+        "e^(2x)sin(3x)",
+        "e^(-x)cos(x)",
+        // Distribution
+        "x(x+1)(x-2)",
+        "(x+sin(x))e^x",
+    ];
+    for func in funcs {
+        let f = F1D::new(func).unwrap();
+        let result = f.antiderivative().unwrap_or_else(|| panic!("{}", func));
+        // Differentiates back to the integrand
+        let der = result.derive(1);
+        for x in [0.3, 0.7, 1.4, 2.2] {
+            let (expected, got) = (f.eval(x), der.eval(x));
+            assert!(
+                (got - expected).abs() <= 1e-9 * (1. + expected.abs()),
+                "{} at {}: {} != {}",
+                func,
+                x,
+                got,
+                expected
+            );
+        }
+    }
+    // Holds on both sides of the poles of 1/(1-(x/4)^2)
+    let f = f1d!("1/(1-(x/4)^2)");
+    let der = f.antiderivative().unwrap().derive(1);
+    for x in [-7., 5., 9.] {
+        assert!((der.eval(x) - f.eval(x)).abs() < 1e-12, "{}", x);
+    }
+
+    for func in ["e^(x^2)", "x^x", "sin(x)/x", "1/ln(x)", "sqrt(sin(x))"] {
+        assert_eq!(F1D::new(func).unwrap().antiderivative(), None, "{}", func);
+    }
+}
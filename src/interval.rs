@@ -0,0 +1,417 @@
+use std::f64::consts::{E, FRAC_PI_2, PI, TAU};
+
+use num_traits::ToPrimitive;
+
+use crate::{FType, Func, F1D, F2D, F3D, FND};
+
+// Error of libm functions, in ulps, covered by the outward rounding
+const LIBM_ULPS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Closed interval [lo, hi], the bounds can be infinite
+/// ```
+/// use ruut_functions::{f1d, Interval, F1D};
+///
+/// let range = f1d!("x^2-2x").eval_interval(Interval::new(2., 3.));
+/// assert!(range.contains(0.) && range.contains(3.));
+/// ```
+pub struct Interval {
+    /// Lower bound
+    pub lo: f64,
+    /// Upper bound
+    pub hi: f64,
+}
+
+impl Interval {
+    /// Every real number
+    pub const ENTIRE: Interval = Interval {
+        lo: f64::NEG_INFINITY,
+        hi: f64::INFINITY,
+    };
+    /// No number at all, the result of evaluating outside of the domain
+    pub const EMPTY: Interval = Interval {
+        lo: f64::INFINITY,
+        hi: f64::NEG_INFINITY,
+    };
+
+    /// Creates the interval [lo, hi]
+    pub fn new(lo: f64, hi: f64) -> Self {
+        assert!(lo <= hi, "Interval bounds must be ordered");
+        Interval { lo, hi }
+    }
+    /// Interval containing a single number
+    pub fn point(val: f64) -> Self {
+        Interval::new(val, val)
+    }
+    /// True if val is in the interval
+    pub fn contains(&self, val: f64) -> bool {
+        self.lo <= val && val <= self.hi
+    }
+    /// True if the interval has no numbers
+    pub fn is_empty(&self) -> bool {
+        self.lo > self.hi || self.lo.is_nan() || self.hi.is_nan()
+    }
+
+    // [lo, hi] moved outwards by some ulps
+    fn outward(lo: f64, hi: f64, ulps: u32) -> Self {
+        if lo.is_nan() || hi.is_nan() {
+            return Interval::ENTIRE;
+        }
+        let (lo, hi) = (0..ulps).fold((lo, hi), |(lo, hi), _| (lo.next_down(), hi.next_up()));
+        Interval { lo, hi }
+    }
+
+    // Interval of a constant that is only known up to rounding
+    fn around(val: f64) -> Self {
+        Interval::outward(val, val, 1)
+    }
+
+    fn intersect(self, lo: f64, hi: f64) -> Self {
+        let result = Interval {
+            lo: self.lo.max(lo),
+            hi: self.hi.min(hi),
+        };
+        if result.is_empty() {
+            Interval::EMPTY
+        } else {
+            result
+        }
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        if self.is_empty() || rhs.is_empty() {
+            return Interval::EMPTY;
+        }
+        Interval::outward(self.lo + rhs.lo, self.hi + rhs.hi, 1)
+    }
+
+    fn mul(self, rhs: Self) -> Self {
+        if self.is_empty() || rhs.is_empty() {
+            return Interval::EMPTY;
+        }
+        // 0 * inf is 0, bounds are limits and not values. Products by zero are exact and
+        // don't need rounding
+        let prod = |a: f64, b: f64| {
+            if a == 0. || b == 0. {
+                Interval::point(0.)
+            } else {
+                Interval::outward(a * b, a * b, 1)
+            }
+        };
+        [
+            prod(self.lo, rhs.lo),
+            prod(self.lo, rhs.hi),
+            prod(self.hi, rhs.lo),
+            prod(self.hi, rhs.hi),
+        ]
+        .iter()
+        .fold(Interval::EMPTY, |acc, prod| Interval {
+            lo: acc.lo.min(prod.lo),
+            hi: acc.hi.max(prod.hi),
+        })
+    }
+
+    // 1/x, with extended bounds when x touches or contains zero
+    fn recip(self) -> Self {
+        if self.is_empty() || (self.lo == 0. && self.hi == 0.) {
+            Interval::EMPTY
+        } else if self.lo > 0. || self.hi < 0. {
+            Interval::outward(1. / self.hi, 1. / self.lo, 1)
+        } else if self.lo == 0. {
+            Interval::outward(1. / self.hi, f64::INFINITY, 1)
+        } else if self.hi == 0. {
+            Interval::outward(f64::NEG_INFINITY, 1. / self.lo, 1)
+        } else {
+            // Union of two half lines, its hull is everything
+            Interval::ENTIRE
+        }
+    }
+
+    // f(x) rounded outwards, f(0) = 0 is exact
+    fn image(f: &impl Fn(f64) -> f64, x: f64) -> Self {
+        let y = f(x);
+        if x == 0. && y == 0. {
+            Interval::point(0.)
+        } else {
+            Interval::outward(y, y, LIBM_ULPS)
+        }
+    }
+
+    // f increasing on the interval
+    fn increasing(self, f: impl Fn(f64) -> f64) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        Interval {
+            lo: Interval::image(&f, self.lo).lo,
+            hi: Interval::image(&f, self.hi).hi,
+        }
+    }
+
+    // f decreasing on the interval
+    fn decreasing(self, f: impl Fn(f64) -> f64) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        Interval {
+            lo: Interval::image(&f, self.hi).lo,
+            hi: Interval::image(&f, self.lo).hi,
+        }
+    }
+
+    // f with a minimum at 0, decreasing before and increasing after
+    fn valley(self, f: impl Fn(f64) -> f64) -> Self {
+        if self.is_empty() || self.lo >= 0. {
+            self.increasing(f)
+        } else if self.hi <= 0. {
+            self.decreasing(f)
+        } else {
+            Interval {
+                lo: Interval::image(&f, 0.).lo,
+                hi: Interval::image(&f, self.lo)
+                    .hi
+                    .max(Interval::image(&f, self.hi).hi),
+            }
+        }
+    }
+
+    // sin or cos, given where their maximum and minimum are in [0, 2pi)
+    fn periodic(self, f: impl Fn(f64) -> f64, max_at: f64, min_at: f64) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        // Also when a bound is infinite
+        if self.hi - self.lo >= TAU {
+            return Interval::new(-1., 1.);
+        }
+        let mut result = Interval::outward(
+            f(self.lo).min(f(self.hi)),
+            f(self.lo).max(f(self.hi)),
+            LIBM_ULPS,
+        );
+        if self.hits(max_at, TAU) {
+            result.hi = 1.;
+        }
+        if self.hits(min_at, TAU) {
+            result.lo = -1.;
+        }
+        result.intersect(-1., 1.)
+    }
+
+    // True if the interval might contain offset + k * period, erring on the safe side
+    fn hits(self, offset: f64, period: f64) -> bool {
+        let k = ((self.lo - offset) / period).ceil();
+        let slack = 4. * f64::EPSILON * (self.lo.abs().max(self.hi.abs()) + period);
+        [k - 1., k, k + 1.].iter().any(|k| {
+            let point = offset + k * period;
+            self.lo - slack <= point && point <= self.hi + slack
+        })
+    }
+
+    // tan or cot, increasing or decreasing between poles
+    fn branches(self, f: impl Fn(f64) -> f64, pole_at: f64, increasing: bool) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        if self.hi - self.lo >= PI || self.hits(pole_at, PI) {
+            return Interval::ENTIRE;
+        }
+        let result = if increasing {
+            self.increasing(f)
+        } else {
+            self.decreasing(f)
+        };
+        // cot is 1 / tan, one more rounding
+        Interval::outward(result.lo, result.hi, 1)
+    }
+
+    fn apply(self, kind: FType) -> Self {
+        if self.is_empty() {
+            return self;
+        }
+        match kind {
+            FType::Sin => self.periodic(f64::sin, FRAC_PI_2, -FRAC_PI_2),
+            FType::Cos => self.periodic(f64::cos, 0., PI),
+            FType::Tan => self.branches(f64::tan, FRAC_PI_2, true),
+            FType::Cot => self.branches(|x| 1. / x.tan(), 0., false),
+            FType::Sec => self.apply(FType::Cos).recip(),
+            FType::Csc => self.apply(FType::Sin).recip(),
+            FType::ASin => self.intersect(-1., 1.).increasing(f64::asin),
+            FType::ACos => self.intersect(-1., 1.).decreasing(f64::acos),
+            FType::ATan => self.increasing(f64::atan),
+            FType::Sinh => self.increasing(f64::sinh),
+            FType::Cosh => self.valley(f64::cosh),
+            FType::Tanh => self.increasing(f64::tanh),
+            FType::Coth => self.apply(FType::Tanh).recip(),
+            FType::Sech => self.apply(FType::Cosh).recip(),
+            FType::Csch => self.apply(FType::Sinh).recip(),
+            FType::ASinh => self.increasing(f64::asinh),
+            FType::ACosh => self.intersect(1., f64::INFINITY).increasing(f64::acosh),
+            FType::ATanh => self.intersect(-1., 1.).increasing(f64::atanh),
+            FType::Abs => self.valley(f64::abs),
+            FType::Ln => self.intersect(0., f64::INFINITY).increasing(f64::ln),
+        }
+    }
+
+    fn pow(self, exp: Self) -> Self {
+        if self.is_empty() || exp.is_empty() {
+            return Interval::EMPTY;
+        }
+        if exp.lo == exp.hi {
+            let exp = exp.lo;
+            if exp == 0. {
+                return Interval::point(1.);
+            }
+            if exp > 0. && self.lo == 0. && self.hi == 0. {
+                return self;
+            }
+            if exp.fract() == 0. && exp.abs() < 2f64.powi(53) {
+                // Integer power, negative bases are fine
+                if exp < 0. {
+                    return self.pow(Interval::point(-exp)).recip();
+                }
+                return if exp % 2. == 0. {
+                    self.valley(|x| x.powf(exp))
+                } else {
+                    self.increasing(|x| x.powf(exp))
+                };
+            }
+            let base = self.intersect(0., f64::INFINITY);
+            return if exp > 0. {
+                base.increasing(|x| x.powf(exp))
+            } else {
+                base.decreasing(|x| x.powf(exp))
+            };
+        }
+        if self.lo == self.hi && self.lo > 0. {
+            let base = self.lo;
+            return if base >= 1. {
+                exp.increasing(|x| base.powf(x))
+            } else {
+                exp.decreasing(|x| base.powf(x))
+            };
+        }
+        // x^y = e^(y ln(x))
+        exp.mul(self.apply(FType::Ln)).increasing(f64::exp)
+    }
+}
+
+impl Func {
+    fn eval_interval(&self, val: &[(&str, Interval)]) -> Interval {
+        match self {
+            Func::Var(var) => val
+                .iter()
+                .find(|&x| x.0 == var)
+                .map_or(Interval::ENTIRE, |x| x.1),
+            Func::Num(num) => Interval::point(*num as f64),
+            Func::Float(num) => Interval::point(*num),
+            Func::Rational(num) => Interval::around(num.to_f64().unwrap_or(f64::NAN)),
+            Func::E => Interval::around(E),
+            Func::PI => Interval::around(PI),
+            Func::Param(_, num) => Interval::point(*num),
+            Func::Add(add) => add.iter().fold(Interval::point(0.), |acc, term| {
+                acc.add(term.eval_interval(val))
+            }),
+            Func::Mul(mul) => mul.iter().fold(Interval::point(1.), |acc, term| {
+                acc.mul(term.eval_interval(val))
+            }),
+            Func::Pow(base, exp) => {
+                let exp = exp.eval_interval(val);
+                match **base {
+                    Func::E => exp.increasing(f64::exp),
+                    _ => base.eval_interval(val).pow(exp),
+                }
+            }
+            Func::S(kind, arg) => arg.eval_interval(val).apply(*kind),
+        }
+    }
+}
+
+impl F1D {
+    /// Bounds of the function for x in the interval, the result contains every value the
+    /// function takes there
+    /// ```
+    /// use ruut_functions::{f1d, Interval, F1D};
+    ///
+    /// let range = f1d!("1/x").eval_interval(Interval::new(0., 2.));
+    /// assert!(range.contains(0.5) && range.hi == f64::INFINITY);
+    /// ```
+    pub fn eval_interval(&self, x: Interval) -> Interval {
+        self.0.eval_interval(&[("x", x)])
+    }
+}
+impl F2D {
+    /// Bounds of the function over the box x × y
+    pub fn eval_interval(&self, x: Interval, y: Interval) -> Interval {
+        self.0.eval_interval(&[("x", x), ("y", y)])
+    }
+}
+impl F3D {
+    /// Bounds of the function over the box x × y × z
+    pub fn eval_interval(&self, x: Interval, y: Interval, z: Interval) -> Interval {
+        self.0.eval_interval(&[("x", x), ("y", y), ("z", z)])
+    }
+}
+impl FND {
+    /// Bounds of the function over a box, variables without an interval can take any value
+    pub fn eval_interval(&self, val: &[(&str, Interval)]) -> Interval {
+        self.func.eval_interval(val)
+    }
+}
+
+#[test]
+fn test_interval() {
+    use crate::{f1d, f2d, f3d};
+
+    let i = Interval::new;
+    assert_eq!(f1d!("1/x").eval_interval(i(-1., 1.)), Interval::ENTIRE);
+    assert!(f1d!("1/x").eval_interval(i(0., 0.)).is_empty());
+    let neg = f1d!("1/x").eval_interval(i(-2., 0.));
+    assert_eq!(neg.lo, f64::NEG_INFINITY);
+    assert!(neg.contains(-0.5) && !neg.contains(-0.4));
+    assert!(f1d!("ln(x)").eval_interval(i(-2., -1.)).is_empty());
+    assert_eq!(
+        f1d!("ln(x)").eval_interval(i(-2., 1.)).lo,
+        f64::NEG_INFINITY
+    );
+    assert!(f1d!("asin(x)").eval_interval(i(2., 3.)).is_empty());
+    assert_eq!(f1d!("tan(x)").eval_interval(i(1., 2.)), Interval::ENTIRE);
+    assert_eq!(f1d!("sin(x)").eval_interval(i(-100., 100.)), i(-1., 1.));
+    assert_eq!(f1d!("cos(x)").eval_interval(i(-0.5, 0.5)).hi, 1.);
+    let square = f1d!("x^2").eval_interval(i(-1., 2.));
+    assert!(square.lo == 0. && square.contains(4.) && square.hi < 4.0001);
+    assert!(!f1d!("x^3").eval_interval(i(-1., 2.)).contains(-1.001));
+    assert!(f1d!("x^0.5").eval_interval(i(-4., 4.)).contains(2.));
+    assert_eq!(f1d!("abs(x)").eval_interval(i(-3., 2.)).lo, 0.);
+
+    // Every sampled value is inside the bounds
+    let funcs = [
+        f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)cos(14x)+3/2-1/7"),
+        f1d!("e^(-x/2)+sqrt(x)+asin(x/4)acos(x/5)+atan(x)"),
+        f1d!("sinh(x)cosh(x)tanh(x)+coth(x)-sech(x)+csch(x)+asinh(x)+acosh(x)+atanh(x/3)"),
+        f1d!("cot(x)+sec(x)^2-csc(3x)+abs(x-1)+x^x+2^x+x^(-3)"),
+    ];
+    let boxes = [
+        (0.1, 0.2),
+        (0.5, 2.),
+        (1., 1.),
+        (-3., 4.),
+        (2.9, 3.3),
+        (1e-9, 1e-3),
+    ];
+    for func in &funcs {
+        for (lo, hi) in boxes {
+            let range = func.eval_interval(i(lo, hi));
+            for k in 0..=200 {
+                let x = lo + (hi - lo) * k as f64 / 200.;
+                let val = func.eval(x);
+                assert!(val.is_nan() || range.contains(val), "{} at {}", func, x);
+            }
+        }
+    }
+
+    let range = f2d!("xy-y^2").eval_interval(i(1., 2.), i(-1., 3.));
+    assert!(range.contains(2. * 3. - 9.) && range.contains(-2.));
+    let range = f3d!("xyz+[a]").eval_interval(i(1., 2.), i(1., 2.), i(1., 2.));
+    assert!(range.lo <= 1. && range.hi >= 8. && range.hi < 8.0001);
+}
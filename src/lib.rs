@@ -1,19 +1,53 @@
 #![deny(missing_docs)]
 //! Crate for creating math functions from string and perform symbolic derivation
-use parser::{builder::build, to_rpn, ParsingError};
+use num_rational::BigRational;
+use parser::parse;
+pub use parser::{Expected, ParsingError, ParsingErrorKind};
 use simp::simp_node;
 
+mod batch;
+mod collect;
+mod compile;
+pub use crate::compile::Compiled;
+mod complex;
+pub use crate::complex::Complex;
 mod derivation;
+pub use crate::derivation::{curl, divergence, Partials};
 mod display;
+mod dual;
 mod eval;
+mod expand;
+mod factor;
 mod integration;
-pub use crate::eval::{eval_vec_f1d, eval_vec_f2d, eval_vec_f3d};
+pub use crate::integration::{Integral, IntegrationError, Quadrature};
+mod interval;
+pub use crate::eval::{eval_vec_f1d, eval_vec_f2d, eval_vec_f3d, EvalError, Operation};
+pub use crate::interval::Interval;
 mod macros;
 mod ops;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod param;
 mod parser;
+mod poly;
+mod rational;
+mod reverse;
 mod simp;
+mod taylor;
 mod traits;
+mod vector;
+pub use crate::vector::{FVec, MismatchedVars};
+
+// Functions are shared across threads by the parallel feature
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+    send_sync::<F1D>();
+    send_sync::<F2D>();
+    send_sync::<F3D>();
+    send_sync::<FND>();
+    send_sync::<FVec>();
+    send_sync::<Compiled>();
+};
 
 #[derive(Debug, PartialEq)]
 /// Representation of a 1D function
@@ -28,7 +62,7 @@ pub struct F3D(Func);
 impl F1D {
     /// Creates a new function from a str
     pub fn new(input: &str) -> Result<Self, ParsingError> {
-        let mut func = build(to_rpn(input, &['x'])?);
+        let mut func = parse(input, &["x"])?;
         simp_node(&mut func);
         Ok(F1D(func))
     }
@@ -40,7 +74,7 @@ impl F1D {
 impl F2D {
     /// Creates a new function from a str
     pub fn new(input: &str) -> Result<Self, ParsingError> {
-        let mut func = build(to_rpn(input, &['x', 'y'])?);
+        let mut func = parse(input, &["x", "y"])?;
         simp_node(&mut func);
         Ok(F2D(func))
     }
@@ -54,7 +88,7 @@ impl F2D {
 impl F3D {
     /// Creates a new funcction from a str
     pub fn new(input: &str) -> Result<Self, ParsingError> {
-        let mut func = build(to_rpn(input, &['x', 'y', 'z'])?);
+        let mut func = parse(input, &["x", "y", "z"])?;
         simp_node(&mut func);
         Ok(F3D(func))
     }
@@ -65,12 +99,18 @@ impl F3D {
 }
 
 impl FND {
-    /// Creates a new function from a string
-    pub fn new(input: &str, vars: &[char]) -> Result<Self, ParsingError> {
-        let mut func = build(to_rpn(input, vars)?);
+    /// Creates a new function from a string, variables can be any identifier
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("theta x+v_x^2", &["theta", "x", "v_x"]);
+    /// assert_eq!(f.eval(&[("theta", 2.), ("x", 3.), ("v_x", 4.)]), 22.);
+    /// ```
+    pub fn new(input: &str, vars: &[&str]) -> Result<Self, ParsingError> {
+        let mut func = parse(input, vars)?;
         simp_node(&mut func);
         Ok(FND {
-            vars: vars.to_vec(),
+            vars: vars.iter().map(|var| var.to_string()).collect(),
             func,
         })
     }
@@ -79,16 +119,19 @@ impl FND {
 #[derive(Debug, PartialEq)]
 /// Representation of an n-dimensional function
 pub struct FND {
-    vars: Vec<char>,
+    vars: Vec<String>,
     func: Func,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Func {
-    Var(char),
+    Var(String),
     E,
     PI,
     Num(i32),
+    Float(f64),
+    // Exact constant that doesn't fit in Num: never an i32 integer, always reduced
+    Rational(BigRational),
     Param(String, f64),
     Add(Vec<Self>),
     Mul(Vec<Self>),
@@ -96,40 +139,47 @@ pub(crate) enum Func {
     S(FType, Box<Self>),
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub(crate) enum FType {
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Elementary function of one argument
+pub enum FType {
+    /// Sine
     Sin,
+    /// Cosine
     Cos,
+    /// Tangent
     Tan,
+    /// Cotangent
     Cot,
+    /// Secant
     Sec,
+    /// Cosecant
     Csc,
+    /// Arcsine
     ASin,
+    /// Arccosine
     ACos,
+    /// Arctangent
     ATan,
+    /// Hyperbolic sine
     Sinh,
+    /// Hyperbolic cosine
     Cosh,
+    /// Hyperbolic tangent
     Tanh,
+    /// Hyperbolic cotangent
     Coth,
+    /// Hyperbolic secant
     Sech,
+    /// Hyperbolic cosecant
     Csch,
+    /// Inverse hyperbolic sine
     ASinh,
+    /// Inverse hyperbolic cosine
     ACosh,
+    /// Inverse hyperbolic tangent
     ATanh,
+    /// Absolute value
     Abs,
+    /// Natural logarithm
     Ln,
 }
-
-pub(crate) fn gcd(mut n: u32, mut m: u32) -> u32 {
-    assert!(n != 0 && m != 0);
-    if n == 0 {
-        std::mem::swap(&mut n, &mut m);
-    }
-    while m != 0 {
-        if m < n {
-            std::mem::swap(&mut m, &mut n);
-        }
-        m %= n;
-    }
-    n
-}
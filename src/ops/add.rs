@@ -1,6 +1,9 @@
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 
-use crate::{simp::simp_node, Func};
+use crate::{
+    simp::{fold_consts, simp_node},
+    Func,
+};
 
 impl Add for Func {
     type Output = Self;
@@ -12,12 +15,14 @@ impl Add for Func {
         if rhs == 0 {
             return self;
         }
+        if let Some(sum) = fold_consts(&self, &rhs, |a, b| a + b) {
+            return sum;
+        }
 
         let mut func = match (&self, &rhs) {
-            (Func::Num(val), Func::Num(val2)) => Func::Num(val + val2),
             (Func::Add(add1), Func::Add(add2)) => Func::Add([&add1[..], &add2[..]].concat()),
             (Func::Add(add1), other) | (other, Func::Add(add1)) => {
-                Func::Add([&add1[..], &[other.clone()]].concat())
+                Func::Add([&add1[..], std::slice::from_ref(other)].concat())
             }
             (_, _) => Func::Add(vec![self, rhs]),
         };
@@ -29,8 +34,12 @@ impl Add for Func {
 
 impl AddAssign for Func {
     fn add_assign(&mut self, rhs: Self) {
+        if let Some(sum) = fold_consts(self, &rhs, |a, b| a + b) {
+            *self = sum;
+            return;
+        }
+
         match (&mut *self, &rhs) {
-            (Func::Num(val), Func::Num(val2)) => *val += val2,
             (Func::Add(add_lhs), Func::Add(add_rhs)) => {
                 for el in add_rhs {
                     add_lhs.push(el.clone());
@@ -39,6 +48,9 @@ impl AddAssign for Func {
             (Func::Add(add), other) => {
                 add.push(other.clone());
             }
+            (_, Func::Add(add_rhs)) => {
+                *self = Func::Add([std::slice::from_ref(self), &add_rhs[..]].concat());
+            }
             (_, _) => *self = Func::Add(vec![self.clone(), rhs]),
         };
 
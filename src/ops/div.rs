@@ -34,7 +34,7 @@ impl Div for Func {
             (Func::Mul(mul), other) => Func::Mul([&mul[..], &[other.clone().powi(-1)]].concat()),
             (other, Func::Mul(div)) => Func::Mul(
                 [
-                    &[other.clone()],
+                    std::slice::from_ref(other),
                     &div.iter()
                         .map(|el| el.clone().powi(-1))
                         .collect::<Vec<Func>>()[..],
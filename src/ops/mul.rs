@@ -1,6 +1,9 @@
 use std::ops::{Mul, MulAssign};
 
-use crate::{simp::simp_node, Func};
+use crate::{
+    simp::{fold_consts, simp_node},
+    Func,
+};
 
 impl Mul for Func {
     type Output = Self;
@@ -15,12 +18,14 @@ impl Mul for Func {
         if self == 0 || rhs == 0 {
             return Func::Num(0);
         }
+        if let Some(prod) = fold_consts(&self, &rhs, |a, b| a * b) {
+            return prod;
+        }
 
         let mut func = match (&self, &rhs) {
-            (Func::Num(mul1), Func::Num(mul2)) => Func::Num(mul1 * mul2),
             (Func::Mul(mul1), Func::Mul(mul2)) => Func::Mul([&mul1[..], &mul2[..]].concat()),
             (Func::Mul(mul), other) | (other, Func::Mul(mul)) => {
-                Func::Mul([&mul[..], &[other.clone()]].concat())
+                Func::Mul([&mul[..], std::slice::from_ref(other)].concat())
             }
             (_, _) => Func::Mul(vec![self, rhs]),
         };
@@ -31,8 +36,12 @@ impl Mul for Func {
 
 impl MulAssign for Func {
     fn mul_assign(&mut self, rhs: Self) {
+        if let Some(prod) = fold_consts(self, &rhs, |a, b| a * b) {
+            *self = prod;
+            return;
+        }
+
         match (&mut *self, &rhs) {
-            (Func::Num(val), Func::Num(val2)) => *val *= val2,
             (Func::Mul(mul_lhs), Func::Mul(mul_rhs)) => {
                 for el in mul_rhs {
                     mul_lhs.push(el.clone())
@@ -41,6 +50,11 @@ impl MulAssign for Func {
             (Func::Mul(mul), _) => {
                 mul.push(rhs);
             }
+            (_, Func::Mul(mul_rhs)) => {
+                if *self != 0 {
+                    *self = Func::Mul([std::slice::from_ref(self), &mul_rhs[..]].concat());
+                }
+            }
             (_, _) => {
                 if *self == 0 {
                     return;
@@ -1,4 +1,4 @@
-use crate::{FType, Func};
+use crate::{simp::fold_pow, FType, Func};
 
 impl Func {
     pub(crate) fn pow(self, exp: Self) -> Self {
@@ -8,6 +8,9 @@ impl Func {
         if exp == 0 {
             return Func::Num(1);
         }
+        if let Some(folded) = fold_pow(&self, &exp) {
+            return folded;
+        }
 
         if let Func::E = self {
             if let Func::S(FType::Ln, arg) = exp {
@@ -25,6 +28,9 @@ impl Func {
         if exp == 1 {
             return self;
         }
+        if let Some(folded) = fold_pow(&self, &Func::Num(exp)) {
+            return folded;
+        }
 
         Func::Pow(Box::new(self), Box::new(Func::Num(exp)))
     }
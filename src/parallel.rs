@@ -0,0 +1,189 @@
+use rayon::prelude::*;
+
+use crate::{batch::Points, Compiled, F1D, F2D, F3D, FND};
+
+// Points handed to each thread, a multiple of the batch chunk
+const BLOCK: usize = 4096;
+
+impl Compiled {
+    /// Parallel version of [`Compiled::eval_many`]
+    pub fn par_eval_many(&self, cols: &[&[f64]]) -> Vec<f64> {
+        self.par_batch(&Points::columns(cols, self.vars().len()))
+    }
+    /// Parallel version of [`Compiled::eval_grid`]
+    pub fn par_eval_grid(&self, axes: &[&[f64]]) -> Vec<f64> {
+        self.par_batch(&Points::grid(axes, self.vars().len()))
+    }
+
+    fn par_batch(&self, points: &Points) -> Vec<f64> {
+        let mut result = vec![0.; points.len()];
+        result
+            .par_chunks_mut(BLOCK)
+            .enumerate()
+            .for_each(|(i, out)| self.run_batch(points, i * BLOCK, out));
+        result
+    }
+}
+
+impl F1D {
+    /// Evaluates function at every x on all cores
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("x^2").par_eval_many(&[1., 2., 3.]), vec![1., 4., 9.]);
+    /// ```
+    pub fn par_eval_many(&self, x: &[f64]) -> Vec<f64> {
+        self.compile().par_eval_many(&[x])
+    }
+}
+impl F2D {
+    /// Evaluates function at every (x[i], y[i]) on all cores
+    ///
+    /// # Panics
+    /// If x and y have different lengths
+    pub fn par_eval_many(&self, x: &[f64], y: &[f64]) -> Vec<f64> {
+        self.compile().par_eval_many(&[x, y])
+    }
+    /// Evaluates function on the grid x × y on all cores, row-major (y changes fastest)
+    pub fn par_eval_grid(&self, x: &[f64], y: &[f64]) -> Vec<f64> {
+        self.compile().par_eval_grid(&[x, y])
+    }
+    /// Computes the gradient, one entry per thread
+    pub fn par_gradient(&self) -> Vec<Self> {
+        ['x', 'y']
+            .into_par_iter()
+            .map(|var| self.derive(var, 1))
+            .collect()
+    }
+    /// Computes the hessian matrix, one entry per thread
+    pub fn par_hessian(&self) -> Vec<Vec<Self>> {
+        self.par_gradient()
+            .par_iter()
+            .map(|d| {
+                ['x', 'y']
+                    .into_par_iter()
+                    .map(|var| d.derive(var, 1))
+                    .collect()
+            })
+            .collect()
+    }
+}
+impl F3D {
+    /// Evaluates function at every (x[i], y[i], z[i]) on all cores
+    ///
+    /// # Panics
+    /// If x, y and z have different lengths
+    pub fn par_eval_many(&self, x: &[f64], y: &[f64], z: &[f64]) -> Vec<f64> {
+        self.compile().par_eval_many(&[x, y, z])
+    }
+    /// Evaluates function on the grid x × y × z on all cores, row-major (z changes fastest)
+    pub fn par_eval_grid(&self, x: &[f64], y: &[f64], z: &[f64]) -> Vec<f64> {
+        self.compile().par_eval_grid(&[x, y, z])
+    }
+    /// Computes the gradient, one entry per thread
+    pub fn par_gradient(&self) -> Vec<Self> {
+        ['x', 'y', 'z']
+            .into_par_iter()
+            .map(|var| self.derive(var, 1))
+            .collect()
+    }
+    /// Computes the hessian, one entry per thread
+    /// ```
+    /// use ruut_functions::{f3d, F3D};
+    ///
+    /// let f = f3d!("x^3+zy^2");
+    /// assert_eq!(f.par_hessian(), f.hessian());
+    /// ```
+    pub fn par_hessian(&self) -> Vec<Vec<Self>> {
+        self.par_gradient()
+            .par_iter()
+            .map(|d| {
+                ['x', 'y', 'z']
+                    .into_par_iter()
+                    .map(|var| d.derive(var, 1))
+                    .collect()
+            })
+            .collect()
+    }
+}
+impl FND {
+    /// Evaluates function at every point on all cores, each variable comes with its column
+    /// of values
+    ///
+    /// # Panics
+    /// If a variable of the function has no column or the columns have different lengths
+    pub fn par_eval_many(&self, val: &[(&str, &[f64])]) -> Vec<f64> {
+        self.compile().par_eval_many(&self.slots(val))
+    }
+    /// Evaluates function on the tensor product of the axes on all cores, row-major with the
+    /// last variable of the function changing fastest
+    ///
+    /// # Panics
+    /// If a variable of the function has no axis
+    pub fn par_eval_grid(&self, val: &[(&str, &[f64])]) -> Vec<f64> {
+        self.compile().par_eval_grid(&self.slots(val))
+    }
+    /// Computes the gradient, one entry per thread
+    pub fn par_gradient(&self) -> Vec<Self> {
+        self.vars
+            .par_iter()
+            .map(|var| self.derive(var, 1))
+            .collect()
+    }
+    /// Computes the hessian, one entry per thread
+    pub fn par_hessian(&self) -> Vec<Vec<Self>> {
+        self.par_gradient()
+            .par_iter()
+            .map(|d| self.vars.par_iter().map(|var| d.derive(var, 1)).collect())
+            .collect()
+    }
+}
+
+#[test]
+fn test_parallel() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    let xs: Vec<f64> = (0..20_000).map(|i| -3. + i as f64 * 3e-4).collect();
+    let f = f1d!("x+sin(x)/cos(x)+ln(4x)ln(7)+e^(-x/2)");
+    let par = f.par_eval_many(&xs);
+    let serial = f.eval_many(&xs);
+    assert!(par
+        .iter()
+        .zip(&serial)
+        .all(|(a, b)| a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan())));
+
+    let axis: Vec<f64> = (1..30).map(|i| i as f64 * 0.1).collect();
+    let f = f3d!("xyz+atan(x/y)");
+    assert_eq!(
+        f.par_eval_grid(&axis, &axis, &axis),
+        f.eval_grid(&axis, &axis, &axis)
+    );
+    assert_eq!(f.par_gradient(), f.gradient());
+    assert_eq!(f.par_hessian(), f.hessian());
+
+    let f = f2d!("x^3y+sin(y)");
+    assert_eq!(f.par_gradient(), f.gradient());
+    assert_eq!(f.par_hessian(), f.hessian());
+
+    let vars = ["a", "b", "c", "d"];
+    let f = fnd!("a^2b+cd-ln(a)", &vars);
+    assert_eq!(f.par_gradient(), f.gradient());
+    assert_eq!(f.par_hessian(), f.hessian());
+    assert_eq!(
+        f.par_eval_many(&[
+            ("a", &[1., 2.]),
+            ("b", &[1., 1.]),
+            ("c", &[0., 1.]),
+            ("d", &[5., 3.])
+        ]),
+        vec![1., 7. - 2f64.ln()]
+    );
+
+    // The same function shared by many threads
+    let f = f3d!("x^2+y^2+z^2");
+    let sums: Vec<f64> = (0..64)
+        .into_par_iter()
+        .map(|i| f.eval(i as f64, 0., 0.))
+        .collect();
+    assert_eq!(sums[7], 49.);
+}
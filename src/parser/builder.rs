@@ -1,64 +1,102 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, ops::Range};
 
-use crate::Func;
+use num_rational::BigRational;
+use num_traits::Zero;
 
-use super::Grammar;
+use crate::{simp::as_rational, Func};
 
-pub(crate) fn build(input: VecDeque<Grammar>) -> Func {
+use super::{Grammar, ParsingError, ParsingErrorKind};
+
+pub(crate) fn build(input: VecDeque<(Grammar, Range<usize>)>) -> Result<Func, ParsingError> {
     let mut stack: Vec<Func> = Vec::with_capacity(4);
-    for el in input {
+    for (el, span) in input {
         match el {
             Grammar::Num(val) => stack.push(Func::Num(val)),
-            Grammar::Var(char) => stack.push(Func::Var(char)),
+            Grammar::BigInt(val) => stack.push(Func::Rational(BigRational::from_integer(val))),
+            Grammar::Float(val) => stack.push(Func::Float(val)),
+            Grammar::Var(name) => stack.push(Func::Var(name)),
             Grammar::Param(name) => stack.push(Func::Param(name, 0.)),
             Grammar::Add => {
-                let second = stack.pop().unwrap();
-
-                *stack.last_mut().unwrap() += second;
+                let second = pop(&mut stack, &span)?;
+                let mut first = pop(&mut stack, &span)?;
+                first += second;
+                stack.push(first);
             }
             Grammar::Sub => {
-                let second = stack.pop().unwrap();
-                let first = stack.pop();
-
-                if let Some(el) = first {
-                    stack.push(el - second);
-                } else {
-                    stack.push(-1 * second)
-                }
+                let second = pop(&mut stack, &span)?;
+                let first = pop(&mut stack, &span)?;
+                stack.push(first - second);
             }
             Grammar::Mul => {
-                let second = stack.pop().unwrap();
-
-                *stack.last_mut().unwrap() *= second;
+                let second = pop(&mut stack, &span)?;
+                let mut first = pop(&mut stack, &span)?;
+                first *= second;
+                stack.push(first);
             }
             Grammar::Div => {
-                let first = stack.pop().unwrap();
-                let second = stack.pop().unwrap();
+                let first = pop(&mut stack, &span)?;
+                let second = pop(&mut stack, &span)?;
+                if is_zero(&first) {
+                    return Err(ParsingError::new(
+                        ParsingErrorKind::DivisionByZero,
+                        span,
+                        &[],
+                    ));
+                }
                 stack.push(second / first);
             }
             Grammar::Pow => {
-                let second = stack.pop().unwrap();
-                let first = stack.pop().unwrap();
+                let second = pop(&mut stack, &span)?;
+                let first = pop(&mut stack, &span)?;
                 stack.push(first.pow(second))
             }
             Grammar::E => stack.push(Func::E),
             Grammar::PI => stack.push(Func::PI),
             Grammar::S(kind) => {
-                let arg = stack.pop().unwrap();
+                let arg = pop(&mut stack, &span)?;
                 stack.push(Func::S(kind, Box::new(arg)));
             }
             Grammar::Sqrt => {
-                let arg = stack.pop().unwrap();
-                stack.push(arg.pow(Func::Mul(vec![
-                    Func::Num(1),
-                    Func::Pow(Box::new(Func::Num(2)), Box::new(Func::Num(-1))),
-                ])));
+                let arg = pop(&mut stack, &span)?;
+                stack.push(arg.pow(Func::Rational(BigRational::new(1.into(), 2.into()))));
+            }
+            // Never emitted by to_rpn
+            Grammar::LPar | Grammar::Neg => {
+                return Err(ParsingError::new(
+                    ParsingErrorKind::NotMatchingPar,
+                    span,
+                    &[],
+                ))
             }
-            Grammar::LPar => panic!("Found left par in RPN representation"),
         }
     }
 
-    stack.pop().unwrap()
+    match (stack.pop(), stack.is_empty()) {
+        (Some(func), true) => Ok(func),
+        _ => Err(ParsingError::new(
+            ParsingErrorKind::MissingOperand,
+            0..0,
+            &[],
+        )),
+    }
+}
+
+// Folded divisor equal to zero, whether exact or a float
+fn is_zero(func: &Func) -> bool {
+    match func {
+        Func::Float(val) => *val == 0.,
+        _ => as_rational(func).is_some_and(|val| val.is_zero()),
+    }
+}
+
+fn pop(stack: &mut Vec<Func>, span: &Range<usize>) -> Result<Func, ParsingError> {
+    stack.pop().ok_or_else(|| {
+        ParsingError::new(
+            ParsingErrorKind::MissingOperand,
+            span.clone(),
+            &[super::Expected::Operand],
+        )
+    })
 }
 
 #[test]
@@ -67,34 +105,31 @@ fn test_builder() {
     use crate::FType;
 
     assert_eq!(
-        build(to_rpn("cos(-x)+sqrt(x)+(x^(-3))^2", &['x']).unwrap()),
-        build(to_rpn("cos(-x)+x^(1/2)+x^(-6)", &['x']).unwrap()),
+        build(to_rpn("cos(-x)+sqrt(x)+(x^(-3))^2", &["x"]).unwrap()).unwrap(),
+        build(to_rpn("cos(-x)+x^(1/2)+x^(-6)", &["x"]).unwrap()).unwrap(),
     );
 
-    let input = to_rpn("(sin(3+7)/8)-7^2", &['x']).unwrap();
+    let input = to_rpn("(sin(3+7)/8)-7^2", &["x"]).unwrap();
     assert_eq!(
-        build(input),
+        build(input).unwrap(),
         Func::Add(vec![
+            Func::Num(-49),
             Func::Mul(vec![
+                Func::Rational(BigRational::new(1.into(), 8.into())),
                 Func::S(FType::Sin, Box::new(Func::Num(10))),
-                Func::Pow(Box::new(Func::Num(8)), Box::new(Func::Num(-1))),
             ]),
-            Func::Mul(vec![
-                Func::Num(-1),
-                Func::Pow(Box::new(Func::Num(7)), Box::new(Func::Num(2)))
-            ])
         ])
     );
 
     assert_eq!(
-        build(to_rpn("e^(-[eta]xy)", &['x', 'y']).unwrap()),
+        build(to_rpn("e^(-[eta]xy)", &["x", "y"]).unwrap()).unwrap(),
         Func::Pow(
             Box::new(Func::E),
             Box::new(Func::Mul(vec![
                 Func::Num(-1),
                 Func::Param("eta".to_string(), 0.),
-                Func::Var('x'),
-                Func::Var('y')
+                Func::Var(String::from("x")),
+                Func::Var(String::from("y"))
             ]))
         )
     )
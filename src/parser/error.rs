@@ -0,0 +1,159 @@
+use std::{fmt::Display, ops::Range};
+
+#[derive(Debug, PartialEq, Clone)]
+/// Error returned when a string can't be turned into a function
+/// ```
+/// use ruut_functions::{F1D, ParsingErrorKind};
+///
+/// let err = F1D::new("x+").unwrap_err();
+/// assert_eq!(err.kind(), &ParsingErrorKind::MissingOperand);
+/// assert_eq!(err.span(), 2..2);
+/// assert_eq!(format!("{}", err), "missing operand, expected an operand\nx+\n  ^");
+/// ```
+pub struct ParsingError {
+    kind: ParsingErrorKind,
+    span: Range<usize>,
+    expected: Vec<Expected>,
+    input: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// What went wrong while parsing
+pub enum ParsingErrorKind {
+    /// A parenthesis is never closed or was never opened
+    NotMatchingPar,
+    /// Name followed by '(' that isn't a known function
+    UnknownFunction(String),
+    /// Name that isn't a variable, a constant or a function
+    UnknownIdentifier(String),
+    /// Operator without a left or right hand side
+    MissingOperand,
+    /// Function called without argument, like "sin()"
+    EmptyArgument(String),
+    /// Parameter without closing bracket, like "[a"
+    UnterminatedParam,
+    /// Parameter without a name, "[]"
+    EmptyParam,
+    /// Malformed number, like "1.2.3"
+    BadLiteral(String),
+    /// Character or token that doesn't fit where it is
+    UnexpectedToken(String),
+    /// Division by something that simplifies to zero
+    DivisionByZero,
+    /// Nothing to parse
+    EmptyInput,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Token the parser was looking for when it failed
+pub enum Expected {
+    /// A number, variable, constant, parameter, function or '('
+    Operand,
+    /// A binary operator
+    Operator,
+    /// '('
+    LPar,
+    /// ')'
+    RPar,
+    /// ']'
+    RBracket,
+    /// The end of the input
+    End,
+}
+
+impl ParsingError {
+    pub(crate) fn new(kind: ParsingErrorKind, span: Range<usize>, expected: &[Expected]) -> Self {
+        ParsingError {
+            kind,
+            span,
+            expected: expected.to_vec(),
+            input: String::new(),
+        }
+    }
+
+    pub(crate) fn with_input(mut self, input: &str) -> Self {
+        self.input = input.to_string();
+        self
+    }
+
+    /// Kind of error
+    pub fn kind(&self) -> &ParsingErrorKind {
+        &self.kind
+    }
+    /// Byte range of the input where the error was found
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+    /// Tokens that would have been valid at the error position
+    pub fn expected(&self) -> &[Expected] {
+        &self.expected
+    }
+}
+
+impl Display for ParsingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotMatchingPar => write!(f, "mismatched parenthesis"),
+            Self::UnknownFunction(name) => write!(f, "unknown function `{}`", name),
+            Self::UnknownIdentifier(name) => write!(f, "unknown identifier `{}`", name),
+            Self::MissingOperand => write!(f, "missing operand"),
+            Self::EmptyArgument(name) => write!(f, "empty argument in `{}()`", name),
+            Self::UnterminatedParam => write!(f, "unterminated parameter"),
+            Self::EmptyParam => write!(f, "empty parameter name"),
+            Self::BadLiteral(literal) => write!(f, "invalid number `{}`", literal),
+            Self::UnexpectedToken(token) => write!(f, "unexpected `{}`", token),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::EmptyInput => write!(f, "empty input"),
+        }
+    }
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Operand => write!(f, "an operand"),
+            Self::Operator => write!(f, "an operator"),
+            Self::LPar => write!(f, "'('"),
+            Self::RPar => write!(f, "')'"),
+            Self::RBracket => write!(f, "']'"),
+            Self::End => write!(f, "end of input"),
+        }
+    }
+}
+
+impl Display for ParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        for (i, expected) in self.expected.iter().enumerate() {
+            if i == 0 {
+                write!(f, ", expected {}", expected)?;
+            } else {
+                write!(f, " or {}", expected)?;
+            }
+        }
+
+        // Columns are counted in chars so that the caret lines up with non-ascii input
+        let start = self
+            .input
+            .get(..self.span.start)
+            .unwrap_or("")
+            .chars()
+            .count();
+        let width = self
+            .input
+            .get(self.span.clone())
+            .unwrap_or("")
+            .chars()
+            .count()
+            .max(1);
+        write!(
+            f,
+            "\n{}\n{}{}",
+            self.input,
+            " ".repeat(start),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::error::Error for ParsingError {}
@@ -1,12 +1,19 @@
 pub mod builder;
+mod error;
 
-use std::collections::VecDeque;
+pub use error::{Expected, ParsingError, ParsingErrorKind};
 
-use crate::FType;
+use std::{collections::VecDeque, ops::Range};
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::{FType, Func};
+use builder::build;
 
 #[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Grammar {
-    Var(char),
+    Var(String),
     Add,
     Sub,
     Mul,
@@ -15,208 +22,425 @@ pub(crate) enum Grammar {
     E,
     PI,
     LPar,
+    // Unary minus, only lives on the operator stack and leaves it as Mul
+    Neg,
     Num(i32),
+    // Integer literal too big for Num
+    BigInt(BigInt),
+    Float(f64),
     Param(String),
     S(FType),
     Sqrt,
 }
-pub(crate) fn to_rpn(input: &str, vars: &[char]) -> Result<VecDeque<Grammar>, ParsingError> {
-    let mut curr = String::new();
-    let mut chars = input.chars().peekable();
 
-    let mut output_queue: VecDeque<Grammar> = VecDeque::new();
-    let mut operator_stack: Vec<Grammar> = Vec::new();
-    let mut prev = None;
-    let mut found_param = false;
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Operand(Grammar),
+    // Function and the name it was called with
+    Func(Grammar, String),
+    Op(char),
+    LPar,
+    RPar,
+}
+
+pub(crate) fn parse(input: &str, vars: &[&str]) -> Result<Func, ParsingError> {
+    to_rpn(input, vars)
+        .and_then(build)
+        .map_err(|err| err.with_input(input))
+}
 
-    while let Some(char) = chars.next() {
-        if char == ' ' {
-            continue;
+pub(crate) fn to_rpn(
+    input: &str,
+    vars: &[&str],
+) -> Result<VecDeque<(Grammar, Range<usize>)>, ParsingError> {
+    let tokens = tokenize(input, vars)?;
+    check_parentheses(&tokens)?;
+
+    let mut output_queue = VecDeque::new();
+    let mut operator_stack: Vec<(Grammar, Range<usize>)> = Vec::new();
+    let mut expect_operand = true;
+
+    for (i, (token, span)) in tokens.iter().enumerate() {
+        // Implicit multiplication: "2x", "x(x+1)", "(x)sin(x)"
+        if !expect_operand && matches!(token, Token::Operand(_) | Token::Func(..) | Token::LPar) {
+            push_operator(
+                Grammar::Mul,
+                span.start..span.start,
+                &mut operator_stack,
+                &mut output_queue,
+            );
         }
-        curr.push(char);
-        let next = chars.peek();
-
-        if curr.parse::<i32>().is_ok() {
-            if let Some(char) = next {
-                if !char.is_ascii_digit() {
-                    output_queue.push_back(Grammar::Num(curr.parse::<i32>().unwrap()));
-                    implicit_mul(&next, &mut operator_stack, &mut output_queue);
-                    curr.clear();
+
+        match token {
+            Token::Operand(operand) => {
+                output_queue.push_back((operand.clone(), span.clone()));
+                expect_operand = false;
+            }
+            Token::Func(func, _) => {
+                operator_stack.push((func.clone(), span.clone()));
+                expect_operand = true;
+            }
+            Token::LPar => {
+                operator_stack.push((Grammar::LPar, span.clone()));
+                expect_operand = true;
+            }
+            Token::RPar => {
+                if expect_operand {
+                    return Err(empty_par_error(&tokens[..i], span.clone()));
                 }
-            } else {
-                output_queue.push_back(Grammar::Num(curr.parse::<i32>().unwrap()));
+                while let Some((op, op_span)) = operator_stack.pop() {
+                    if op == Grammar::LPar {
+                        break;
+                    }
+                    output_queue.push_back((to_output(op), op_span));
+                }
+                expect_operand = false;
+            }
+            Token::Op(op) => {
+                if expect_operand {
+                    match op {
+                        '-' => {
+                            output_queue.push_back((Grammar::Num(-1), span.clone()));
+                            operator_stack.push((Grammar::Neg, span.clone()));
+                        }
+                        '+' => (),
+                        _ => {
+                            return Err(ParsingError::new(
+                                ParsingErrorKind::MissingOperand,
+                                span.clone(),
+                                &[Expected::Operand],
+                            ))
+                        }
+                    }
+                    continue;
+                }
+                let op = match op {
+                    '+' => Grammar::Add,
+                    '-' => Grammar::Sub,
+                    '*' => Grammar::Mul,
+                    '/' => Grammar::Div,
+                    _ => Grammar::Pow,
+                };
+                push_operator(op, span.clone(), &mut operator_stack, &mut output_queue);
+                expect_operand = true;
             }
-        } else if curr == "e" {
-            output_queue.push_back(Grammar::E);
-            curr.clear();
-        } else if curr == "pi" {
-            output_queue.push_back(Grammar::PI);
-            implicit_mul(&next, &mut operator_stack, &mut output_queue);
-            curr.clear();
-        } else if curr == "[" {
-            found_param = true;
-        } else if char == ']' {
-            output_queue.push_back(Grammar::Param(curr[1..(curr.len() - 1)].to_string()));
-            found_param = false;
-            implicit_mul(&next, &mut operator_stack, &mut output_queue);
-            curr.clear();
-        } else if curr.len() == 1 && vars.contains(&curr.chars().next().unwrap()) {
-            output_queue.push_back(Grammar::Var(char));
-            curr.clear();
-            implicit_mul(&next, &mut operator_stack, &mut output_queue);
-        } else if !found_param
-            && (match_operator(char, &next, &prev, &mut operator_stack, &mut output_queue)?
-                || match_func(&curr, &next, &mut operator_stack)?)
-        {
-            curr.clear();
         }
+    }
 
-        prev = Some(char);
+    if expect_operand {
+        return Err(if tokens.is_empty() {
+            ParsingError::new(ParsingErrorKind::EmptyInput, 0..0, &[Expected::Operand])
+        } else {
+            ParsingError::new(
+                ParsingErrorKind::MissingOperand,
+                input.len()..input.len(),
+                &[Expected::Operand],
+            )
+        });
     }
 
-    while !operator_stack.is_empty() {
-        if let Grammar::LPar = operator_stack.last().unwrap() {
-            return Err(ParsingError::NotMatchingPar);
-        }
-        output_queue.push_back(operator_stack.pop().unwrap());
+    while let Some((op, span)) = operator_stack.pop() {
+        output_queue.push_back((to_output(op), span));
     }
 
     Ok(output_queue)
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ParsingError {
-    NotMatchingPar,
-    UnknownFunction(String),
-}
+fn tokenize(input: &str, vars: &[&str]) -> Result<Vec<(Token, Range<usize>)>, ParsingError> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
 
-fn implicit_mul(
-    next: &Option<&char>,
-    operator_stack: &mut Vec<Grammar>,
-    output_queue: &mut VecDeque<Grammar>,
-) {
-    if let Some(n) = next {
-        if !matches!(n, '+' | '-' | '/' | '*' | '^' | ' ' | ')') {
-            let _ = match_operator('*', &None, &None, operator_stack, output_queue);
+    while let Some(char) = input[pos..].chars().next() {
+        if char.is_whitespace() {
+            pos += char.len_utf8();
+            continue;
         }
-    }
-}
 
-fn match_operator(
-    curr: char,
-    next: &Option<&char>,
-    prev: &Option<char>,
-    operator_stack: &mut Vec<Grammar>,
-    output_queue: &mut VecDeque<Grammar>,
-) -> Result<bool, ParsingError> {
-    let o1 = match curr {
-        '+' => Grammar::Add,
-        '-' => {
-            if let Some(p) = prev {
-                if *p == '(' {
-                    output_queue.push_back(Grammar::Num(-1));
-                    Grammar::Mul
-                } else {
-                    Grammar::Sub
+        let (token, len) = match char {
+            '0'..='9' | '.' => {
+                let literal = read_number(&input[pos..]);
+                match parse_number(literal) {
+                    Some(num) => (Token::Operand(num), literal.len()),
+                    None => {
+                        return Err(ParsingError::new(
+                            ParsingErrorKind::BadLiteral(literal.to_string()),
+                            pos..pos + literal.len(),
+                            &[],
+                        ))
+                    }
                 }
-            } else {
-                output_queue.push_back(Grammar::Num(-1));
-                Grammar::Mul
             }
-        }
-        '*' => Grammar::Mul,
-        '/' => Grammar::Div,
-        '^' => Grammar::Pow,
-        '(' => {
-            operator_stack.push(Grammar::LPar);
-            return Ok(true);
-        }
-        ')' => {
-            while !operator_stack.is_empty() {
-                match operator_stack.last() {
-                    Some(o2) => {
-                        if let Grammar::LPar = o2 {
-                            break;
-                        }
-                        output_queue.push_back(operator_stack.pop().unwrap());
-                    }
-                    _ => break,
+            '[' => {
+                let len = input[pos..].find(']').ok_or_else(|| {
+                    ParsingError::new(
+                        ParsingErrorKind::UnterminatedParam,
+                        pos..input.len(),
+                        &[Expected::RBracket],
+                    )
+                })? + 1;
+                let name: String = input[pos + 1..pos + len - 1]
+                    .chars()
+                    .filter(|char| !char.is_whitespace())
+                    .collect();
+                if name.is_empty() {
+                    return Err(ParsingError::new(
+                        ParsingErrorKind::EmptyParam,
+                        pos..pos + len,
+                        &[],
+                    ));
                 }
+                (Token::Operand(Grammar::Param(name)), len)
             }
-
-            if let Some(Grammar::LPar) = operator_stack.last() {
-                operator_stack.pop();
-            } else {
-                return Err(ParsingError::NotMatchingPar);
+            '(' => (Token::LPar, 1),
+            ')' => (Token::RPar, 1),
+            '+' | '-' | '*' | '/' | '^' => (Token::Op(char), 1),
+            _ if is_ident_char(char) => read_identifier(input, pos, vars)?,
+            _ => {
+                return Err(ParsingError::new(
+                    ParsingErrorKind::UnexpectedToken(char.to_string()),
+                    pos..pos + char.len_utf8(),
+                    &[],
+                ))
             }
-            implicit_mul(next, operator_stack, output_queue);
-            return Ok(true);
+        };
+
+        tokens.push((token, pos..pos + len));
+        pos += len;
+    }
+
+    Ok(tokens)
+}
+
+fn is_ident_char(char: char) -> bool {
+    char.is_alphabetic() || char == '_'
+}
+
+fn read_identifier(input: &str, pos: usize, vars: &[&str]) -> Result<(Token, usize), ParsingError> {
+    let rest = &input[pos..];
+    if let Some(var) = match_var(rest, vars) {
+        return Ok((Token::Operand(Grammar::Var(var.to_string())), var.len()));
+    }
+
+    let word_len = rest.find(|char| !is_ident_char(char)).unwrap_or(rest.len());
+    let after_word = rest[word_len..].trim_start();
+    if let Some(func) = func_from_name(&rest[..word_len]) {
+        if after_word.starts_with('(') {
+            return Ok((Token::Func(func, rest[..word_len].to_string()), word_len));
         }
+    }
+
+    // An unknown name stops where a declared variable starts: "ex" is e*x
+    let known_len = rest[..word_len]
+        .char_indices()
+        .skip(1)
+        .find(|(i, _)| match_var(&rest[*i..], vars).is_some())
+        .map_or(word_len, |(i, _)| i);
+    let word = &rest[..known_len];
+
+    if rest.starts_with("pi") {
+        return Ok((Token::Operand(Grammar::PI), 2));
+    }
+    if rest.starts_with('e') {
+        return Ok((Token::Operand(Grammar::E), 1));
+    }
+
+    let span = pos..pos + known_len;
+    if func_from_name(word).is_some() {
+        // Function name not followed by an argument
+        let next = pos + known_len + rest[known_len..].len() - rest[known_len..].trim_start().len();
+        return Err(match input[next..].chars().next() {
+            Some(char) => ParsingError::new(
+                ParsingErrorKind::UnexpectedToken(char.to_string()),
+                next..next + char.len_utf8(),
+                &[Expected::LPar],
+            ),
+            None => ParsingError::new(
+                ParsingErrorKind::EmptyArgument(word.to_string()),
+                span,
+                &[Expected::LPar],
+            ),
+        });
+    }
+    if known_len == word_len && after_word.starts_with('(') {
+        return Err(ParsingError::new(
+            ParsingErrorKind::UnknownFunction(word.to_string()),
+            span,
+            &[],
+        ));
+    }
+
+    Err(ParsingError::new(
+        ParsingErrorKind::UnknownIdentifier(word.to_string()),
+        span,
+        &[],
+    ))
+}
+
+// Longest declared variable at the start of `rest`. Function calls and the constants
+// e and pi win over variables that are not longer than them
+fn match_var<'a>(rest: &str, vars: &[&'a str]) -> Option<&'a str> {
+    let var = vars
+        .iter()
+        .filter(|var| !var.is_empty() && rest.starts_with(**var))
+        .max_by_key(|var| var.len())?;
+
+    let word_len = rest.find(|char| !is_ident_char(char)).unwrap_or(rest.len());
+    if rest[word_len..].trim_start().starts_with('(') && func_from_name(&rest[..word_len]).is_some()
+    {
+        return None;
+    }
 
-        _ => return Ok(false),
+    let constant_len = if rest.starts_with("pi") {
+        2
+    } else if rest.starts_with('e') {
+        1
+    } else {
+        0
     };
+    if var.len() <= constant_len {
+        return None;
+    }
 
-    while !operator_stack.is_empty() {
-        match operator_stack.last() {
-            Some(o2) => {
-                if let Grammar::LPar = o2 {
-                    break;
-                }
+    Some(var)
+}
 
-                if op_prec(o2).0 > op_prec(&o1).0
-                    || (op_prec(o2).0 == op_prec(&o1).0 && op_prec(&o1).1)
-                {
-                    output_queue.push_back(operator_stack.pop().unwrap());
-                } else {
-                    break;
-                }
+// Every ')' must close a '(' and every '(' must be closed
+fn check_parentheses(tokens: &[(Token, Range<usize>)]) -> Result<(), ParsingError> {
+    let mut open = Vec::new();
+    for (token, span) in tokens {
+        match token {
+            Token::LPar => open.push(span.clone()),
+            Token::RPar if open.pop().is_none() => {
+                return Err(ParsingError::new(
+                    ParsingErrorKind::NotMatchingPar,
+                    span.clone(),
+                    &[Expected::Operator, Expected::End],
+                ));
             }
-            _ => break,
+            _ => (),
         }
     }
-    operator_stack.push(o1);
 
-    Ok(true)
+    match open.pop() {
+        Some(span) => Err(ParsingError::new(
+            ParsingErrorKind::NotMatchingPar,
+            span,
+            &[Expected::RPar],
+        )),
+        None => Ok(()),
+    }
+}
+
+// ')' found where an operand was expected: "sin()", "()" or "x+)"
+fn empty_par_error(before: &[(Token, Range<usize>)], span: Range<usize>) -> ParsingError {
+    match before {
+        [.., (Token::Func(_, name), func_span), (Token::LPar, _)] => ParsingError::new(
+            ParsingErrorKind::EmptyArgument(name.clone()),
+            func_span.start..span.end,
+            &[Expected::Operand],
+        ),
+        [.., (Token::LPar, par_span)] => ParsingError::new(
+            ParsingErrorKind::MissingOperand,
+            par_span.start..span.end,
+            &[Expected::Operand],
+        ),
+        _ => ParsingError::new(ParsingErrorKind::MissingOperand, span, &[Expected::Operand]),
+    }
 }
 
-fn match_func(
-    curr: &str,
-    next: &Option<&char>,
-    operator_stack: &mut Vec<Grammar>,
-) -> Result<bool, ParsingError> {
-    if let Some(char) = next {
-        if **char == '(' {
-            operator_stack.push(match curr {
-                "sin" => Grammar::S(FType::Sin),
-                "cos" => Grammar::S(FType::Cos),
-                "tan" => Grammar::S(FType::Tan),
-                "cot" => Grammar::S(FType::Cot),
-                "sec" => Grammar::S(FType::Sec),
-                "csc" => Grammar::S(FType::Csc),
-                "asin" => Grammar::S(FType::ASin),
-                "acos" => Grammar::S(FType::ACos),
-                "atan" => Grammar::S(FType::ATan),
-                "sinh" => Grammar::S(FType::Sinh),
-                "cosh" => Grammar::S(FType::Cosh),
-                "tanh" => Grammar::S(FType::Tanh),
-                "coth" => Grammar::S(FType::Coth),
-                "sech" => Grammar::S(FType::Sech),
-                "csch" => Grammar::S(FType::Csch),
-                "asinh" => Grammar::S(FType::ASinh),
-                "acosh" => Grammar::S(FType::ACosh),
-                "atanh" => Grammar::S(FType::ATanh),
-                "abs" => Grammar::S(FType::Abs),
-                "ln" | "log" => Grammar::S(FType::Ln),
-                "sqrt" => Grammar::Sqrt,
-                _ => return Err(ParsingError::UnknownFunction(curr.to_string())),
-            });
-            return Ok(true);
+fn push_operator(
+    o1: Grammar,
+    span: Range<usize>,
+    operator_stack: &mut Vec<(Grammar, Range<usize>)>,
+    output_queue: &mut VecDeque<(Grammar, Range<usize>)>,
+) {
+    while let Some((o2, _)) = operator_stack.last() {
+        if *o2 == Grammar::LPar {
+            break;
         }
+
+        if op_prec(o2).0 > op_prec(&o1).0 || (op_prec(o2).0 == op_prec(&o1).0 && op_prec(&o1).1) {
+            if let Some((o2, o2_span)) = operator_stack.pop() {
+                output_queue.push_back((to_output(o2), o2_span));
+            }
+        } else {
+            break;
+        }
+    }
+    operator_stack.push((o1, span));
+}
+
+// The -1 of a unary minus is already in the output queue
+fn to_output(op: Grammar) -> Grammar {
+    if op == Grammar::Neg {
+        Grammar::Mul
     } else {
-        return Err(ParsingError::UnknownFunction(curr.to_string()));
+        op
     }
+}
+
+// Reads a numeric literal: digits, an optional fractional part and an optional
+// exponent ("1e-3"). An 'e' not followed by digits is left alone so that "2e" is 2*e
+fn read_number(rest: &str) -> &str {
+    let mut len = rest
+        .find(|char: char| !(char.is_ascii_digit() || char == '.'))
+        .unwrap_or(rest.len());
 
-    Ok(false)
+    if rest[len..].starts_with('e') {
+        let exponent = &rest[len + 1..];
+        let sign = usize::from(exponent.starts_with(['+', '-']));
+        let digits = exponent[sign..]
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(exponent.len() - sign);
+        if digits > 0 {
+            len += 1 + sign + digits;
+        }
+    }
+
+    &rest[..len]
+}
+
+// Integers stay exact, decimals and scientific notation become floats
+fn parse_number(literal: &str) -> Option<Grammar> {
+    if !literal.contains(['.', 'e']) {
+        let val = literal.parse::<BigInt>().ok()?;
+        return Some(match val.to_i32() {
+            Some(val) => Grammar::Num(val),
+            None => Grammar::BigInt(val),
+        });
+    }
+
+    match literal.parse::<f64>() {
+        Ok(val) if val.is_finite() => Some(Grammar::Float(val)),
+        _ => None,
+    }
+}
+
+fn func_from_name(name: &str) -> Option<Grammar> {
+    Some(match name {
+        "sin" => Grammar::S(FType::Sin),
+        "cos" => Grammar::S(FType::Cos),
+        "tan" => Grammar::S(FType::Tan),
+        "cot" => Grammar::S(FType::Cot),
+        "sec" => Grammar::S(FType::Sec),
+        "csc" => Grammar::S(FType::Csc),
+        "asin" => Grammar::S(FType::ASin),
+        "acos" => Grammar::S(FType::ACos),
+        "atan" => Grammar::S(FType::ATan),
+        "sinh" => Grammar::S(FType::Sinh),
+        "cosh" => Grammar::S(FType::Cosh),
+        "tanh" => Grammar::S(FType::Tanh),
+        "coth" => Grammar::S(FType::Coth),
+        "sech" => Grammar::S(FType::Sech),
+        "csch" => Grammar::S(FType::Csch),
+        "asinh" => Grammar::S(FType::ASinh),
+        "acosh" => Grammar::S(FType::ACosh),
+        "atanh" => Grammar::S(FType::ATanh),
+        "abs" => Grammar::S(FType::Abs),
+        "ln" | "log" => Grammar::S(FType::Ln),
+        "sqrt" => Grammar::Sqrt,
+        _ => return None,
+    })
 }
 
 // Returns if it's left-associative
@@ -224,27 +448,36 @@ fn op_prec(op: &Grammar) -> (usize, bool) {
     match op {
         Grammar::Add | Grammar::Sub => (2, true),
         Grammar::Mul | Grammar::Div => (3, true),
-        Grammar::Pow => (4, false),
-        _ => (5, true),
+        Grammar::Neg => (4, false),
+        Grammar::Pow => (5, false),
+        _ => (6, true),
     }
 }
 
 #[test]
 fn test_to_rpn() {
+    fn rpn(input: &str, vars: &[&str]) -> VecDeque<Grammar> {
+        to_rpn(input, vars)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
     assert_eq!(
-        to_rpn("sin(x)/cos(-x)", &['x']).unwrap(),
+        rpn("sin(x)/cos(-x)", &["x"]),
         VecDeque::from([
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
             Grammar::S(FType::Sin),
             Grammar::Num(-1),
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
             Grammar::Mul,
             Grammar::S(FType::Cos),
             Grammar::Div,
         ])
     );
     assert_eq!(
-        to_rpn("-13/(15+7^3)*sinh(69)+x+e^x+pi", &['x']).unwrap(),
+        rpn("-13/(15+7^3)*sinh(69)+x+e^x+pi", &["x"]),
         VecDeque::from([
             Grammar::Num(-1),
             Grammar::Num(13),
@@ -258,10 +491,10 @@ fn test_to_rpn() {
             Grammar::Num(69),
             Grammar::S(FType::Sinh),
             Grammar::Mul,
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
             Grammar::Add,
             Grammar::E,
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
             Grammar::Pow,
             Grammar::Add,
             Grammar::PI,
@@ -270,24 +503,24 @@ fn test_to_rpn() {
     );
 
     assert_eq!(
-        to_rpn("3*(2-))7", &['x']).unwrap_err(),
-        ParsingError::NotMatchingPar
+        *to_rpn("3*(2-))7", &["x"]).unwrap_err().kind(),
+        ParsingErrorKind::NotMatchingPar
     );
     assert_eq!(
-        to_rpn("3*2((7", &['x']).unwrap_err(),
-        ParsingError::NotMatchingPar
+        *to_rpn("3*2((7", &["x"]).unwrap_err().kind(),
+        ParsingErrorKind::NotMatchingPar
     );
     assert_eq!(
-        to_rpn("yomama(x)", &['x']).unwrap_err(),
-        ParsingError::UnknownFunction("yomama".to_string())
+        *to_rpn("yomama(x)", &["x"]).unwrap_err().kind(),
+        ParsingErrorKind::UnknownFunction("yomama".to_string())
     );
 
     // Implicit multiplication
     assert_eq!(
-        to_rpn("x(x+1)[par]", &['x']).unwrap(),
+        rpn("x(x+1)[par]", &["x"]),
         VecDeque::from([
-            Grammar::Var('x'),
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
+            Grammar::Var(String::from("x")),
             Grammar::Num(1),
             Grammar::Add,
             Grammar::Mul,
@@ -297,29 +530,172 @@ fn test_to_rpn() {
     );
     // Implicit multiply
     assert_eq!(
-        to_rpn(
+        rpn(
             "x+sin(x)/cos(x)+ln(4*x)*ln(7)+sin(x^2)^2+tan(14*x)/cos(14*x)+3/2-1/7+cos(x)*cos(x)+sin(x)*sin(x)+cos(x)*cos(x)",
-            &['x'],
-        ).unwrap(),
-        to_rpn(
+            &["x"],
+        ),
+        rpn(
             "x+sin(x)/cos(x)+ln(4x)ln(7)+sin(x^2)^2+tan(14x)/cos(14x)+3/2-1/7+cos(x)cos(x)+sin(x)sin(x)+cos(x)cos(x)",
-            &['x'],
-        ).unwrap()
+            &["x"],
+        )
+    );
+
+    // Multi-character variables, longest match wins
+    assert_eq!(
+        rpn("theta x+x1x2", &["x", "x1", "x2", "theta"]),
+        VecDeque::from([
+            Grammar::Var(String::from("theta")),
+            Grammar::Var(String::from("x")),
+            Grammar::Mul,
+            Grammar::Var(String::from("x1")),
+            Grammar::Var(String::from("x2")),
+            Grammar::Mul,
+            Grammar::Add
+        ])
+    );
+    assert_eq!(
+        rpn("eta*e+sin(s)", &["eta", "s"]),
+        VecDeque::from([
+            Grammar::Var(String::from("eta")),
+            Grammar::E,
+            Grammar::Mul,
+            Grammar::Var(String::from("s")),
+            Grammar::S(FType::Sin),
+            Grammar::Add
+        ])
+    );
+
+    // Decimal and scientific literals
+    assert_eq!(
+        rpn("0.5x^2+1e-3*sin(x)-6.022e23", &["x"]),
+        VecDeque::from([
+            Grammar::Float(0.5),
+            Grammar::Var(String::from("x")),
+            Grammar::Num(2),
+            Grammar::Pow,
+            Grammar::Mul,
+            Grammar::Float(1e-3),
+            Grammar::Var(String::from("x")),
+            Grammar::S(FType::Sin),
+            Grammar::Mul,
+            Grammar::Add,
+            Grammar::Float(6.022e23),
+            Grammar::Sub
+        ])
+    );
+    assert_eq!(
+        rpn("2e-x", &["x"]),
+        VecDeque::from([
+            Grammar::Num(2),
+            Grammar::E,
+            Grammar::Mul,
+            Grammar::Var(String::from("x")),
+            Grammar::Sub
+        ])
+    );
+    assert_eq!(
+        rpn("3000000000", &["x"]),
+        VecDeque::from([Grammar::BigInt(3000000000u32.into())])
+    );
+    assert_eq!(
+        *to_rpn("1.2.3", &["x"]).unwrap_err().kind(),
+        ParsingErrorKind::BadLiteral("1.2.3".to_string())
     );
 
     // Power problem
     assert_eq!(
-        to_rpn("e^(-[eta]xy)", &['x', 'y']).unwrap(),
+        rpn("e^(-[eta]xy)", &["x", "y"]),
         VecDeque::from([
             Grammar::E,
             Grammar::Num(-1),
             Grammar::Param(String::from("eta")),
             Grammar::Mul,
-            Grammar::Var('x'),
+            Grammar::Var(String::from("x")),
             Grammar::Mul,
-            Grammar::Var('y'),
+            Grammar::Var(String::from("y")),
             Grammar::Mul,
             Grammar::Pow
         ])
     )
 }
+
+#[test]
+fn test_parsing_errors() {
+    use crate::{F1D, F2D, F3D, FND};
+
+    let err = |input: &str| F1D::new(input).unwrap_err();
+
+    assert_eq!(err("x+").kind(), &ParsingErrorKind::MissingOperand);
+    assert_eq!(err("x+").span(), 2..2);
+    assert_eq!(err("*3").kind(), &ParsingErrorKind::MissingOperand);
+    assert_eq!(err("*3").span(), 0..1);
+    assert_eq!(err("*3").expected(), &[Expected::Operand]);
+    assert_eq!(
+        err("1+sin()").kind(),
+        &ParsingErrorKind::EmptyArgument("sin".to_string())
+    );
+    assert_eq!(err("1+sin()").span(), 2..7);
+    assert_eq!(err("2()").kind(), &ParsingErrorKind::MissingOperand);
+    assert_eq!(err("[a").kind(), &ParsingErrorKind::UnterminatedParam);
+    assert_eq!(err("[a").expected(), &[Expected::RBracket]);
+    assert_eq!(err("[ ]").kind(), &ParsingErrorKind::EmptyParam);
+    assert_eq!(
+        err("x+yx").kind(),
+        &ParsingErrorKind::UnknownIdentifier("y".to_string())
+    );
+    assert_eq!(err("x+yx").span(), 2..3);
+    assert_eq!(
+        err("sin x").kind(),
+        &ParsingErrorKind::UnexpectedToken("x".to_string())
+    );
+    assert_eq!(err("sin x").expected(), &[Expected::LPar]);
+    assert_eq!(
+        err("2cos").kind(),
+        &ParsingErrorKind::EmptyArgument("cos".to_string())
+    );
+    assert_eq!(err("x)").kind(), &ParsingErrorKind::NotMatchingPar);
+    assert_eq!(err("x)").span(), 1..2);
+    assert_eq!(err("(x").expected(), &[Expected::RPar]);
+    assert_eq!(
+        err("1..5").kind(),
+        &ParsingErrorKind::BadLiteral("1..5".to_string())
+    );
+    assert_eq!(
+        err("x$2").kind(),
+        &ParsingErrorKind::UnexpectedToken("$".to_string())
+    );
+    assert_eq!(err("1/(2-2)").kind(), &ParsingErrorKind::DivisionByZero);
+    assert_eq!(err("1/0.0").kind(), &ParsingErrorKind::DivisionByZero);
+    assert_eq!(err("x/(0.5-1/2)").kind(), &ParsingErrorKind::DivisionByZero);
+    assert_eq!(err("x/(1/3-1/3)").kind(), &ParsingErrorKind::DivisionByZero);
+    assert_eq!(err("1/(2-2)").span(), 1..2);
+    assert_eq!(err("  ").kind(), &ParsingErrorKind::EmptyInput);
+
+    // Caret under the offending token, counted in chars
+    assert_eq!(
+        format!("{}", FND::new("θ+sin()", &["θ"]).unwrap_err()),
+        "empty argument in `sin()`, expected an operand\nθ+sin()\n  ^^^^^"
+    );
+    assert_eq!(
+        format!("{}", err("x)")),
+        "mismatched parenthesis, expected an operator or end of input\nx)\n ^"
+    );
+
+    // Unary minus after operators
+    assert_eq!(F1D::new("x^-2").unwrap(), F1D::new("x^(-2)").unwrap());
+    assert_eq!(F1D::new("2*-x^2").unwrap(), F1D::new("-2(x^2)").unwrap());
+    assert_eq!(F1D::new("+x").unwrap(), F1D::new("x").unwrap());
+
+    // Malformed inputs never panic
+    for input in [
+        "", "x+", "*3", "sin()", "[a", "]", "(", ")", "()", "x^", "^x", "-", "--", "+-*/^", "sin",
+        "sin(", "sin(x", "sin)x(", "1e+", ".", "..", "x..", "[", "[]", "[a]]", "x y z w", "sqrt()",
+        "ln(,)", "e^", "pi pi(", "((((", "))))", "x**y", "x//y", "3 4 (", "cos(x)(", "abs(-)",
+        "0/0", "x/0", "1/(2-2)", "é", "x\u{301}",
+    ] {
+        assert!(F1D::new(input).is_err(), "{input}");
+        assert!(F2D::new(input).is_err(), "{input}");
+        assert!(F3D::new(input).is_err(), "{input}");
+        assert!(FND::new(input, &["x", "y"]).is_err(), "{input}");
+    }
+}
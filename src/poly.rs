@@ -0,0 +1,515 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    simp::{as_rational, from_rational, simp_node},
+    Func,
+};
+
+// Largest constant whose divisors are searched for rational roots and quadratic factors
+const MAX_DIVISORS_OF: u64 = 1_000_000_000_000;
+// Most candidate roots and quadratics of Kronecker's method tried, times the degree
+const MAX_WORK: usize = 1_000_000;
+// Highest degree of the polynomials built from functions, larger ones are left as they are
+const MAX_DEGREE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+// Univariate polynomial with exact coefficients, the one of x^i at i and no trailing zeros
+pub(crate) struct Poly(Vec<BigRational>);
+
+impl Poly {
+    pub(crate) fn new(mut coefs: Vec<BigRational>) -> Self {
+        while coefs.last().is_some_and(|coef| coef.is_zero()) {
+            coefs.pop();
+        }
+        Poly(coefs)
+    }
+
+    pub(crate) fn constant(val: BigRational) -> Self {
+        Poly::new(vec![val])
+    }
+
+    pub(crate) fn one() -> Self {
+        Poly::constant(BigRational::one())
+    }
+
+    // x - root
+    pub(crate) fn linear(root: BigRational) -> Self {
+        Poly::new(vec![-root, BigRational::one()])
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Zero for constants, the zero polynomial included
+    pub(crate) fn degree(&self) -> usize {
+        self.0.len().saturating_sub(1)
+    }
+
+    pub(crate) fn lead(&self) -> BigRational {
+        self.0.last().cloned().unwrap_or_else(BigRational::zero)
+    }
+
+    pub(crate) fn scale(&self, factor: &BigRational) -> Self {
+        Poly::new(self.0.iter().map(|coef| coef * factor).collect())
+    }
+
+    pub(crate) fn monic(&self) -> Self {
+        match self.is_zero() {
+            true => self.clone(),
+            false => self.scale(&self.lead().recip()),
+        }
+    }
+
+    pub(crate) fn eval(&self, x: &BigRational) -> BigRational {
+        self.0
+            .iter()
+            .rev()
+            .fold(BigRational::zero(), |acc, coef| acc * x + coef)
+    }
+
+    // Value at an integer of a polynomial with integer coefficients
+    fn eval_integer(&self, x: i32) -> BigInt {
+        self.eval(&BigRational::from_integer(x.into())).to_integer()
+    }
+
+    pub(crate) fn derivative(&self) -> Self {
+        Poly::new(
+            self.0
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(i, coef)| coef * BigRational::from_integer(i.into()))
+                .collect(),
+        )
+    }
+
+    // By repeated squaring
+    pub(crate) fn pow(&self, exp: usize) -> Self {
+        let (mut result, mut base, mut exp) = (Poly::one(), self.clone(), exp);
+        while exp > 0 {
+            if exp % 2 == 1 {
+                result = &result * &base;
+            }
+            exp /= 2;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    // Quotient and remainder of the division by a non zero polynomial
+    pub(crate) fn div_rem(&self, div: &Poly) -> (Poly, Poly) {
+        assert!(!div.is_zero(), "Division by the zero polynomial");
+        let mut rem = self.0.clone();
+        if rem.len() < div.0.len() {
+            return (Poly::new(vec![]), self.clone());
+        }
+        let mut quot = vec![BigRational::zero(); rem.len() - div.0.len() + 1];
+        let lead = div.lead();
+        for i in (0..quot.len()).rev() {
+            let coef = &rem[i + div.degree()] / &lead;
+            for (j, d) in div.0.iter().enumerate() {
+                rem[i + j] -= &coef * d;
+            }
+            quot[i] = coef;
+        }
+        (Poly::new(quot), Poly::new(rem))
+    }
+
+    // Monic greatest common divisor
+    pub(crate) fn gcd(&self, other: &Poly) -> Self {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            let rem = a.div_rem(&b).1;
+            a = b;
+            b = rem;
+        }
+        a.monic()
+    }
+
+    // Exact quotient when div divides self
+    pub(crate) fn divide(&self, div: &Poly) -> Option<Self> {
+        let (quot, rem) = self.div_rem(div);
+        rem.is_zero().then_some(quot)
+    }
+
+    // Exact quotient when self divides func, a polynomial in var whose coefficients are
+    // functions of the other variables
+    pub(crate) fn divide_func(&self, func: &Func, var: &str) -> Option<Func> {
+        let mut rem = func.coefficients(var)?;
+        if self.degree() == 0 || rem.len() < self.0.len() || rem.len() > MAX_DEGREE + 1 {
+            return None;
+        }
+        let div: Vec<Func> = self.0.iter().cloned().map(from_rational).collect();
+        let lead = from_rational(self.lead());
+        let mut quot = vec![Func::Num(0); rem.len() - div.len() + 1];
+        for i in (0..quot.len()).rev() {
+            let coef = (rem[i + self.degree()].clone() / lead.clone()).expand();
+            for (j, d) in div.iter().enumerate() {
+                rem[i + j] = (rem[i + j].clone() - coef.clone() * d.clone()).expand();
+            }
+            quot[i] = coef;
+        }
+        if rem.iter().any(|coef| *coef != 0) {
+            return None;
+        }
+        Some(
+            quot.into_iter()
+                .enumerate()
+                .map(|(i, coef)| coef * Func::Var(var.to_string()).powi(i as i32))
+                .sum(),
+        )
+    }
+
+    // Inverse modulo a coprime polynomial, from the extended Euclidean algorithm
+    pub(crate) fn inverse_mod(&self, modulus: &Poly) -> Option<Self> {
+        let (mut r0, mut r1) = (modulus.clone(), self.div_rem(modulus).1);
+        let (mut s0, mut s1) = (Poly::new(vec![]), Poly::one());
+        while !r1.is_zero() {
+            let (quot, rem) = r0.div_rem(&r1);
+            let next = &s0 - &(&quot * &s1);
+            (r0, r1) = (r1, rem);
+            (s0, s1) = (s1, next);
+        }
+        if r0.degree() != 0 || r0.is_zero() {
+            return None;
+        }
+        Some(s0.scale(&r0.lead().recip()).div_rem(modulus).1)
+    }
+
+    // Content and primitive part: self = content * part, where part has coprime integer
+    // coefficients and a positive leading one
+    pub(crate) fn primitive(&self) -> (BigRational, Poly) {
+        if self.is_zero() {
+            return (BigRational::one(), self.clone());
+        }
+        let denom = self
+            .0
+            .iter()
+            .fold(BigInt::one(), |acc, coef| acc.lcm(coef.denom()));
+        let numer = self.0.iter().fold(BigInt::zero(), |acc, coef| {
+            acc.gcd(&(coef.numer() * &denom / coef.denom()))
+        });
+        let mut content = BigRational::new(numer, denom);
+        if self.lead().is_negative() {
+            content = -content;
+        }
+        (content.clone(), self.scale(&content.recip()))
+    }
+
+    // Yun's square-free decomposition of the monic part: (factor, multiplicity) pairs
+    pub(crate) fn square_free(&self) -> Vec<(Poly, usize)> {
+        let mut result = Vec::new();
+        if self.degree() == 0 {
+            return result;
+        }
+        let f = self.monic();
+        let der = f.derivative();
+        let a = f.gcd(&der);
+        let mut b = f.div_rem(&a).0;
+        let mut c = der.div_rem(&a).0;
+        let mut d = &c - &b.derivative();
+        let mut mult = 1;
+        while b.degree() > 0 {
+            let a = b.gcd(&d);
+            b = b.div_rem(&a).0;
+            c = d.div_rem(&a).0;
+            d = &c - &b.derivative();
+            if a.degree() > 0 {
+                result.push((a, mult));
+            }
+            mult += 1;
+        }
+        result
+    }
+
+    // Factors of a square-free primitive integer polynomial over the rationals: the linear
+    // ones from its rational roots, then quadratic ones by Kronecker's method. What's left
+    // stays as one factor
+    pub(crate) fn factor_square_free(&self) -> Vec<Poly> {
+        let mut factors = Vec::new();
+        let mut rest = self.clone();
+
+        while rest.degree() > 0 && rest.0[0].is_zero() {
+            factors.push(Poly::linear(BigRational::zero()));
+            rest = rest.div_rem(&Poly::linear(BigRational::zero())).0;
+        }
+        for root in rest.rational_roots() {
+            let linear = Poly::linear(root).primitive().1;
+            if let Some(quot) = rest.divide(&linear) {
+                factors.push(linear);
+                rest = quot;
+            }
+        }
+        while rest.degree() >= 4 {
+            let Some(quadratic) = rest.quadratic_factor() else {
+                break;
+            };
+            rest = rest.divide(&quadratic).unwrap();
+            factors.push(quadratic);
+        }
+        if rest.degree() > 0 {
+            factors.push(rest);
+        }
+        factors
+    }
+
+    // Candidates p/q with p dividing the constant and q the leading coefficient
+    fn rational_roots(&self) -> Vec<BigRational> {
+        let (Some(ps), Some(qs)) = (divisors(self.0[0].numer()), divisors(self.lead().numer()))
+        else {
+            return vec![];
+        };
+        if 2 * ps.len() * qs.len() * self.degree() > MAX_WORK {
+            return vec![];
+        }
+        // Dividing by qx-p leaves integer coefficients, so q-p divides f(1) and q+p f(-1)
+        let [at_one, at_minus_one] = [1, -1].map(|x| self.eval_integer(x));
+        let mut roots = Vec::new();
+        for q in &qs {
+            for p in &ps {
+                for root in [
+                    BigRational::new(p.clone(), q.clone()),
+                    BigRational::new(-p, q.clone()),
+                ] {
+                    let (p, q) = (root.numer(), root.denom());
+                    if divides(&(q - p), &at_one)
+                        && divides(&(q + p), &at_minus_one)
+                        && !roots.contains(&root)
+                        && self.eval(&root).is_zero()
+                    {
+                        roots.push(root);
+                    }
+                }
+            }
+        }
+        roots
+    }
+
+    // Integer quadratic through (-1,d1),(0,d0),(1,d2) for divisors di of the values there,
+    // that divides self
+    fn quadratic_factor(&self) -> Option<Poly> {
+        let values: Vec<BigInt> = [-1, 0, 1].map(|x| self.eval_integer(x)).to_vec();
+        // A factor's value at 2 divides the one of self
+        let at_two = self.eval_integer(2);
+        let divs: Vec<Vec<BigInt>> = values
+            .iter()
+            .map(|val| {
+                divisors(val).map(|divs| divs.iter().flat_map(|d| [d.clone(), -d]).collect())
+            })
+            .collect::<Option<_>>()?;
+        if divs.iter().map(Vec::len).product::<usize>() * self.degree() > MAX_WORK {
+            return None;
+        }
+        let two = BigInt::from(2);
+        for dm in &divs[0] {
+            for d0 in &divs[1] {
+                for dp in &divs[2] {
+                    // c = d0, b = (dp-dm)/2, a = (dp+dm)/2-d0
+                    let (sum, diff) = (dp + dm, dp - dm);
+                    if sum.is_odd() || diff.is_odd() {
+                        continue;
+                    }
+                    let a = &sum / &two - d0;
+                    if !a.is_positive() {
+                        continue;
+                    }
+                    let b = &diff / &two;
+                    if !divides(&(&a * 4 + &b * 2 + d0), &at_two) {
+                        continue;
+                    }
+                    let quadratic = Poly::new(
+                        [d0.clone(), b, a]
+                            .into_iter()
+                            .map(BigRational::from_integer)
+                            .collect(),
+                    );
+                    if let Some(quot) = self.divide(&quadratic) {
+                        if quot.0.iter().all(BigRational::is_integer) {
+                            return Some(quadratic);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Coefficients of a polynomial in var with numeric coefficients, None for anything else
+    // and past MAX_DEGREE
+    pub(crate) fn from_func(func: &Func, var: &str) -> Option<Self> {
+        if let Some(val) = as_rational(func) {
+            return Some(Poly::constant(val));
+        }
+        match func {
+            Func::Var(name) if name == var => Some(Poly::linear(BigRational::zero())),
+            Func::Add(add) => add
+                .iter()
+                .map(|term| Poly::from_func(term, var))
+                .try_fold(Poly::new(vec![]), |acc, term| Some(&acc + &term?)),
+            Func::Mul(mul) => mul
+                .iter()
+                .map(|factor| Poly::from_func(factor, var))
+                .try_fold(Poly::one(), |acc, factor| {
+                    let factor = factor?;
+                    (acc.degree() + factor.degree() <= MAX_DEGREE).then(|| &acc * &factor)
+                }),
+            Func::Pow(base, exp) => match **exp {
+                Func::Num(exp) if exp >= 0 => {
+                    let base = Poly::from_func(base, var)?;
+                    (base.degree().saturating_mul(exp as usize) <= MAX_DEGREE)
+                        .then(|| base.pow(exp as usize))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Sum of the terms from the highest degree
+    pub(crate) fn to_func(&self, var: &str) -> Func {
+        let mut terms: Vec<Func> = self
+            .0
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, coef)| !coef.is_zero())
+            .map(|(i, coef)| {
+                from_rational(coef.clone()) * Func::Var(var.to_string()).powi(i as i32)
+            })
+            .collect();
+        let mut func = match terms.len() {
+            0 => Func::Num(0),
+            1 => terms.remove(0),
+            _ => Func::Add(terms),
+        };
+        simp_node(&mut func);
+        func
+    }
+}
+
+// Also true for zero dividing zero
+fn divides(div: &BigInt, val: &BigInt) -> bool {
+    match div.is_zero() {
+        true => val.is_zero(),
+        false => (val % div).is_zero(),
+    }
+}
+
+// Positive divisors, None when they are too expensive to find
+fn divisors(n: &BigInt) -> Option<Vec<BigInt>> {
+    let n = n.abs().to_u64().filter(|n| *n <= MAX_DIVISORS_OF)?;
+    let mut small = Vec::new();
+    let mut large = Vec::new();
+    let mut d = 1;
+    while d * d <= n {
+        if n % d == 0 {
+            small.push(BigInt::from(d));
+            if d * d != n {
+                large.push(BigInt::from(n / d));
+            }
+        }
+        d += 1;
+    }
+    small.extend(large.into_iter().rev());
+    Some(small)
+}
+
+impl Add for &Poly {
+    type Output = Poly;
+
+    fn add(self, rhs: Self) -> Poly {
+        let len = self.0.len().max(rhs.0.len());
+        let zero = BigRational::zero();
+        Poly::new(
+            (0..len)
+                .map(|i| self.0.get(i).unwrap_or(&zero) + rhs.0.get(i).unwrap_or(&zero))
+                .collect(),
+        )
+    }
+}
+
+impl Neg for &Poly {
+    type Output = Poly;
+
+    fn neg(self) -> Poly {
+        Poly::new(self.0.iter().map(|coef| -coef).collect())
+    }
+}
+
+impl Sub for &Poly {
+    type Output = Poly;
+
+    fn sub(self, rhs: Self) -> Poly {
+        self + &-rhs
+    }
+}
+
+impl Mul for &Poly {
+    type Output = Poly;
+
+    fn mul(self, rhs: Self) -> Poly {
+        if self.is_zero() || rhs.is_zero() {
+            return Poly::new(vec![]);
+        }
+        let mut coefs = vec![BigRational::zero(); self.0.len() + rhs.0.len() - 1];
+        for (i, lhs) in self.0.iter().enumerate() {
+            for (j, rhs) in rhs.0.iter().enumerate() {
+                coefs[i + j] += lhs * rhs;
+            }
+        }
+        Poly::new(coefs)
+    }
+}
+
+#[test]
+fn test_poly() {
+    let int = |coefs: &[i32]| {
+        Poly::new(
+            coefs
+                .iter()
+                .map(|coef| BigRational::from_integer((*coef).into()))
+                .collect(),
+        )
+    };
+
+    // (x-1)^2(x+2) = x^3-3x+2
+    let f = int(&[2, -3, 0, 1]);
+    assert_eq!(f.gcd(&f.derivative()), int(&[-1, 1]));
+    assert_eq!(f.square_free(), vec![(int(&[2, 1]), 1), (int(&[-1, 1]), 2)]);
+    let (quot, rem) = f.div_rem(&int(&[1, 1]));
+    assert_eq!((quot, rem), (int(&[-2, -1, 1]), int(&[4])));
+
+    let half = BigRational::new(1.into(), 2.into());
+    let (content, part) = int(&[3, 6]).scale(&half).primitive();
+    assert_eq!(
+        (content, part),
+        (BigRational::new(3.into(), 2.into()), int(&[1, 2]))
+    );
+
+    // (2x-1)(x^2+1)(x^2+x+3)
+    let f = &(&int(&[-1, 2]) * &int(&[1, 0, 1])) * &int(&[3, 1, 1]);
+    assert_eq!(
+        f.factor_square_free(),
+        vec![int(&[-1, 2]), int(&[1, 0, 1]), int(&[3, 1, 1])]
+    );
+    // The inverse of 2x modulo x^2+1 is -x/2, x+1 has none modulo x^2-1
+    let inv = int(&[0, 2]).inverse_mod(&int(&[1, 0, 1])).unwrap();
+    assert_eq!(
+        (&inv * &int(&[0, 2])).div_rem(&int(&[1, 0, 1])).1,
+        Poly::one()
+    );
+    assert_eq!(int(&[1, 1]).inverse_mod(&int(&[-1, 0, 1])), None);
+
+    // Irreducible
+    assert_eq!(int(&[-2, 0, 0, 0, 1]).factor_square_free().len(), 1);
+    assert_eq!(int(&[1, 1]).pow(5), int(&[1, 5, 10, 10, 5, 1]));
+    assert_eq!(int(&[1, 1]).pow(0), Poly::one());
+}
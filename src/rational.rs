@@ -0,0 +1,369 @@
+use crate::{poly::Poly, simp::simp_node, Func, F1D, F2D, F3D, FND};
+
+// Numerator and denominator factors (base, positive exponent)
+type Fraction = (Func, Vec<(Func, i32)>);
+
+impl Func {
+    // Numerator over the least common multiple of the denominators
+    fn fraction(&self) -> Fraction {
+        match self {
+            Func::Add(add) => {
+                let fractions: Vec<Fraction> = add.iter().map(|term| term.fraction()).collect();
+                let mut denom: Vec<(Func, i32)> = Vec::new();
+                for (base, exp) in fractions.iter().flat_map(|(_, den)| den) {
+                    match denom.iter_mut().find(|(b, _)| b == base) {
+                        Some((_, max)) => *max = (*max).max(*exp),
+                        None => denom.push((base.clone(), *exp)),
+                    }
+                }
+                let mut terms: Vec<Func> = fractions
+                    .into_iter()
+                    .map(|(numer, den)| {
+                        denom.iter().fold(numer, |acc, (base, exp)| {
+                            let own = den
+                                .iter()
+                                .find(|(b, _)| b == base)
+                                .map_or(0, |(_, exp)| *exp);
+                            acc * base.clone().powi(exp - own)
+                        })
+                    })
+                    .collect();
+                let numer = match terms.len() {
+                    1 => terms.remove(0),
+                    _ => terms.into_iter().sum(),
+                };
+                (numer, denom)
+            }
+            Func::Mul(mul) => {
+                let mut numer = Func::Num(1);
+                let mut denom: Vec<(Func, i32)> = Vec::new();
+                for (num, den) in mul.iter().map(|factor| factor.fraction()) {
+                    numer *= num;
+                    for (base, exp) in den {
+                        match denom.iter_mut().find(|(b, _)| *b == base) {
+                            Some((_, sum)) => *sum += exp,
+                            None => denom.push((base, exp)),
+                        }
+                    }
+                }
+                (numer, denom)
+            }
+            Func::Pow(base, exp) => match **exp {
+                Func::Num(exp) => {
+                    let (numer, denom) = base.fraction();
+                    let scaled = |factors: Vec<(Func, i32)>| {
+                        factors
+                            .into_iter()
+                            .map(|(base, e)| (base, e * exp.abs()))
+                            .collect::<Vec<_>>()
+                    };
+                    if exp > 0 {
+                        (numer.powi(exp), scaled(denom))
+                    } else {
+                        let numer_factors = match numer {
+                            Func::Mul(mul) => mul.into_iter().map(|f| (f, 1)).collect(),
+                            numer => vec![(numer, 1)],
+                        };
+                        let numer = denom
+                            .iter()
+                            .fold(Func::Num(1), |acc, (b, e)| acc * b.clone().powi(e * -exp));
+                        (numer, scaled(numer_factors))
+                    }
+                }
+                _ => (base.together().pow(exp.together()), vec![]),
+            },
+            Func::S(kind, arg) => (Func::S(*kind, Box::new(arg.together())), vec![]),
+            _ => (self.clone(), vec![]),
+        }
+    }
+
+    // One numerator over one denominator
+    pub(crate) fn together(&self) -> Self {
+        let (numer, denom) = self.fraction();
+        numer / product(&denom)
+    }
+
+    // Together, then the common factors of numerator and denominator are divided out: by
+    // their gcd when they are polynomials in one of the vars, by their factors otherwise,
+    // after trying to divide the numerator by the factors of the denominator in one var
+    pub(crate) fn cancel(&self, vars: &[&str]) -> Self {
+        let (numer, denoms) = self.fraction();
+        let denom = product(&denoms);
+        for var in vars {
+            let (Some(num), Some(den)) =
+                (Poly::from_func(&numer, var), Poly::from_func(&denom, var))
+            else {
+                continue;
+            };
+            if num.is_zero() {
+                return Func::Num(0);
+            }
+            let gcd = num.gcd(&den);
+            let (num, den) = (num.divide(&gcd).unwrap(), den.divide(&gcd).unwrap());
+            // Constants go in the numerator, the denominator is primitive
+            let (content, den) = den.primitive();
+            let num = num.scale(&content.recip());
+            return num.to_func(var).factor(vars) / den.to_func(var).factor(vars);
+        }
+        let mut numer = numer.expand();
+        let mut rest = Func::Num(1);
+        let bases = denoms.iter().flat_map(|(base, exp)| {
+            let factors = factors(&base.expand().factor(vars));
+            factors
+                .into_iter()
+                .map(move |(base, own)| (base, own * exp))
+        });
+        for (base, exp) in bases {
+            let div = vars
+                .iter()
+                .find_map(|var| Some((var, Poly::from_func(&base, var)?)));
+            let mut left = exp;
+            if let Some((var, div)) = div {
+                while left > 0 {
+                    let Some(quot) = div.divide_func(&numer, var) else {
+                        break;
+                    };
+                    numer = quot;
+                    left -= 1;
+                }
+            }
+            rest *= base.powi(left);
+        }
+        numer.factor(vars) / rest
+    }
+
+    // Polynomial part plus a sum of numerators of lower degree over powers of the factors of
+    // the denominator, None when it isn't a rational function of var
+    pub(crate) fn apart(&self, var: &str) -> Option<Self> {
+        let (numer, denom) = self.fraction();
+        let numer = Poly::from_func(&numer, var)?;
+        let denom = Poly::from_func(&product(&denom), var)?;
+        let gcd = numer.gcd(&denom);
+        let (numer, denom) = (numer.divide(&gcd)?, denom.divide(&gcd)?);
+
+        let (quot, rem) = numer.div_rem(&denom);
+        let (content, denom) = denom.primitive();
+        let rem = rem.scale(&content.recip());
+        let quot = quot.to_func(var);
+        if rem.is_zero() {
+            return Some(quot);
+        }
+
+        let mut factors = Vec::new();
+        for (square_free, mult) in denom.square_free() {
+            for factor in square_free.primitive().1.factor_square_free() {
+                let power = factor.pow(mult);
+                factors.push((factor, mult, power));
+            }
+        }
+        let mut terms = vec![quot];
+        for (i, (factor, mult, power)) in factors.iter().enumerate() {
+            // rem/denom = sum of rem_i/power_i, rem_i = rem * (denom/power_i)^-1 mod power_i
+            let others = factors
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(Poly::one(), |acc, (_, (_, _, other))| &acc * other);
+            let mut part = (&rem * &others.inverse_mod(power)?).div_rem(power).1;
+            // Digits of part in base factor: part/factor^m = sum of digit/factor^(m-k)
+            let base = factor.to_func(var);
+            for k in 0..*mult {
+                let (rest, digit) = part.div_rem(factor);
+                if !digit.is_zero() {
+                    terms.push(digit.to_func(var) / base.clone().powi((mult - k) as i32));
+                }
+                part = rest;
+            }
+        }
+        let mut result = Func::Add(terms);
+        simp_node(&mut result);
+        Some(result)
+    }
+}
+
+// Bases and exponents of a product
+fn factors(func: &Func) -> Vec<(Func, i32)> {
+    let factors = match func {
+        Func::Mul(mul) => mul.as_slice(),
+        func => std::slice::from_ref(func),
+    };
+    factors
+        .iter()
+        .map(|factor| match factor {
+            Func::Pow(base, exp) => match **exp {
+                Func::Num(exp) if exp > 0 => ((**base).clone(), exp),
+                _ => (factor.clone(), 1),
+            },
+            factor => (factor.clone(), 1),
+        })
+        .collect()
+}
+
+fn product(factors: &[(Func, i32)]) -> Func {
+    factors.iter().fold(Func::Num(1), |acc, (base, exp)| {
+        acc * base.clone().powi(*exp)
+    })
+}
+
+impl F1D {
+    /// Brings sums of fractions over a common denominator
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("1/x+1/(x+1)").together(), f1d!("(2x+1)/(x(x+1))"));
+    /// ```
+    pub fn together(&self) -> Self {
+        F1D(self.0.together())
+    }
+
+    /// Brings the function over a common denominator and divides out the polynomial gcd of
+    /// numerator and denominator, up to degree 64
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("(x^2-1)/(x^2+2x+1)").cancel(), f1d!("(x-1)/(x+1)"));
+    /// assert_eq!(f1d!("x/(x+1)").derive(1).cancel(), f1d!("1/(x+1)^2"));
+    /// ```
+    pub fn cancel(&self) -> Self {
+        F1D(self.0.cancel(&["x"]))
+    }
+
+    /// Decomposes a rational function into a polynomial plus partial fractions over the linear
+    /// and irreducible quadratic factors of the denominator, with exact coefficients. Other
+    /// functions are returned unchanged
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// let f = f1d!("(x+3)/(x^2+3x+2)").apart();
+    /// assert_eq!(format!("{}", f), "2/(1+x)-1/(2+x)");
+    /// ```
+    pub fn apart(&self) -> Self {
+        F1D(self.0.apart("x").unwrap_or_else(|| self.0.clone()))
+    }
+}
+impl F2D {
+    /// Brings sums of fractions over a common denominator
+    pub fn together(&self) -> Self {
+        F2D(self.0.together())
+    }
+
+    /// Brings the function over a common denominator and divides out the common factors of
+    /// numerator and denominator: monomials, contents and the factors of the denominator in
+    /// one variable. There is no multivariate gcd, so factors in several variables stay
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// assert_eq!(f2d!("(x^2y-y)/(xy-y)").cancel(), f2d!("x+1"));
+    /// assert_eq!(f2d!("(x^2-1)/(x-1)+y").cancel(), f2d!("1+x+y"));
+    /// assert_eq!(f2d!("(x^2-y^2)/(x-y)").cancel(), f2d!("(x^2-y^2)/(x-y)"));
+    /// ```
+    pub fn cancel(&self) -> Self {
+        F2D(self.0.cancel(&["x", "y"]))
+    }
+}
+impl F3D {
+    /// Brings sums of fractions over a common denominator
+    pub fn together(&self) -> Self {
+        F3D(self.0.together())
+    }
+
+    /// Brings the function over a common denominator and divides out the common factors of
+    /// numerator and denominator: monomials, contents and the factors of the denominator in
+    /// one variable. Factors in several variables stay
+    pub fn cancel(&self) -> Self {
+        F3D(self.0.cancel(&["x", "y", "z"]))
+    }
+}
+impl FND {
+    /// Brings sums of fractions over a common denominator
+    pub fn together(&self) -> Self {
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.together(),
+        }
+    }
+
+    /// Brings the function over a common denominator and divides out the common factors of
+    /// numerator and denominator: monomials, contents and the factors of the denominator in
+    /// one variable. Factors in several variables stay
+    pub fn cancel(&self) -> Self {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.cancel(&vars),
+        }
+    }
+}
+
+#[test]
+fn test_rational() {
+    use crate::{f1d, f2d};
+
+    let cases = [
+        ("1/x+1/x^2", "(1+x)/x^2"),
+        ("1/(x-1)-1/(x+1)", "2/((-1+x)(1+x))"),
+        ("x/(x+1)+1/(x+1)", "1"),
+        ("(x^3-x)/(x^2+x)", "-1+x"),
+        ("(2x+2)/(4x^2-4)", "1/(2(-1+x))"),
+        ("1/(1+1/x)", "x/(1+x)"),
+        ("(x^2+1)/(x^4-1)", "1/((-1+x)(1+x))"),
+    ];
+    for (input, cancelled) in cases {
+        let f = F1D::new(input).unwrap();
+        assert_eq!(format!("{}", f.cancel()), cancelled, "{}", input);
+        for x in [-0.7, 0.3, 2.5] {
+            assert!((f.cancel().eval(x) - f.eval(x)).abs() < 1e-9, "{}", input);
+            assert!((f.together().eval(x) - f.eval(x)).abs() < 1e-9, "{}", input);
+        }
+    }
+    assert_eq!(
+        f1d!("sin(x)/x+cos(x)").together(),
+        f1d!("(sin(x)+xcos(x))/x")
+    );
+
+    // Derivatives of rational functions come out reduced
+    assert_eq!(
+        f1d!("(x^2+1)/(x-1)").derive(1).cancel(),
+        f1d!("(x^2-2x-1)/(x-1)^2").cancel()
+    );
+
+    assert_eq!(
+        f1d!("(x^1000000+1)/(x-1)").cancel(),
+        f1d!("(x^1000000+1)/(x-1)")
+    );
+
+    assert_eq!(f2d!("x/y+y/x").together(), f2d!("(x^2+y^2)/(xy)"));
+    assert_eq!(f2d!("(xy+y)/(y^2)").cancel(), f2d!("(x+1)/y"));
+    assert_eq!(f2d!("(x^2-1)/((x-1)y)").cancel(), f2d!("(x+1)/y"));
+    // No multivariate gcd: x-y is a factor of both
+    assert_eq!(f2d!("(x^2-y^2)/(x-y)").cancel(), f2d!("(x^2-y^2)/(x-y)"));
+    assert_eq!(f2d!("(x^2-1)/(x-1)+y").cancel(), f2d!("1+x+y"));
+    assert_eq!(
+        format!("{}", f2d!("x/((x+1)^2(y-2))+y/(x+1)").cancel()),
+        "(x-2y-2xy+xy^2+y^2)/((-2+y)(1+x)^2)"
+    );
+    assert_eq!(f2d!("(x^2y+2xy+y)/(x+1)^2").cancel(), f2d!("y"));
+}
+
+#[test]
+fn test_apart() {
+    use crate::f1d;
+
+    let cases = [
+        ("1/(x^2(x+1))", "-1/x+1/(1+x)+1/x^2"),
+        ("(x^3+1)/(x^2-1)", "x+1/(-1+x)"),
+        ("1/(x^3+x)", "-x/(1+x^2)+1/x"),
+        ("(2x^2+3)/(x^2+1)^2", "2/(1+x^2)+1/(1+x^2)^2"),
+        ("1/(2x+4)", "1/(2(2+x))"),
+        ("(x^4+1)/(x^3-x^2)", "2/(-1+x)-1/x^2-1/x+1+x"),
+        ("x^2+1", "1+x^2"),
+    ];
+    for (input, parts) in cases {
+        let f = F1D::new(input).unwrap();
+        assert_eq!(format!("{}", f.apart()), parts, "{}", input);
+        for x in [-0.7, 0.3, 2.5] {
+            assert!((f.apart().eval(x) - f.eval(x)).abs() < 1e-9, "{}", input);
+        }
+    }
+    assert_eq!(f1d!("sin(x)/x").apart(), f1d!("sin(x)/x"));
+}
@@ -0,0 +1,287 @@
+use crate::{compile::has_vars, FType, Func, FND};
+
+// Elementary operation of the forward sweep, arguments are indices of earlier nodes
+enum Node {
+    Const,
+    Var(usize),
+    Add(usize, usize),
+    Mul(usize, usize),
+    // Argument, first and second derivative of the operation at the argument
+    Unary(usize, f64, f64),
+}
+
+// Wengert list of a function at a point
+struct Tape {
+    nodes: Vec<Node>,
+    vals: Vec<f64>,
+    // Derivatives of the nodes along a direction, empty when no direction is given
+    dots: Vec<f64>,
+}
+
+impl Tape {
+    fn new(func: &Func, point: &[(&str, f64)], vars: &[&str], dir: Option<&[f64]>) -> Self {
+        let mut tape = Tape {
+            nodes: Vec::new(),
+            vals: Vec::new(),
+            dots: Vec::new(),
+        };
+        tape.record(func, point, vars, dir);
+        tape
+    }
+
+    fn push(&mut self, node: Node, val: f64, dot: Option<f64>) -> usize {
+        self.nodes.push(node);
+        self.vals.push(val);
+        if let Some(dot) = dot {
+            self.dots.push(dot);
+        }
+        self.nodes.len() - 1
+    }
+
+    // f(arg) given f, f' and f'' at the value of arg
+    fn unary(&mut self, arg: usize, val: f64, d1: f64, d2: f64) -> usize {
+        let dot = self.dots.get(arg).map(|dot| d1 * dot);
+        self.push(Node::Unary(arg, d1, d2), val, dot)
+    }
+
+    fn binary(&mut self, lhs: usize, rhs: usize, add: bool) -> usize {
+        let (l, r) = (self.vals[lhs], self.vals[rhs]);
+        if add {
+            let dot = self.dots.get(lhs).map(|dot| dot + self.dots[rhs]);
+            self.push(Node::Add(lhs, rhs), l + r, dot)
+        } else {
+            let dot = self.dots.get(lhs).map(|dot| dot * r + l * self.dots[rhs]);
+            self.push(Node::Mul(lhs, rhs), l * r, dot)
+        }
+    }
+
+    // Appends the nodes of func and returns the index of its result
+    fn record(
+        &mut self,
+        func: &Func,
+        point: &[(&str, f64)],
+        vars: &[&str],
+        dir: Option<&[f64]>,
+    ) -> usize {
+        let zero = dir.map(|_| 0.);
+        if !has_vars(func) {
+            return self.push(Node::Const, func.eval(point), zero);
+        }
+        match func {
+            Func::Var(name) => {
+                let val = point
+                    .iter()
+                    .find(|&x| x.0 == name)
+                    .unwrap_or_else(|| panic!("{} has no value", name))
+                    .1;
+                match vars.iter().position(|var| var == name) {
+                    Some(slot) => self.push(Node::Var(slot), val, dir.map(|dir| dir[slot])),
+                    None => self.push(Node::Const, val, zero),
+                }
+            }
+            Func::Add(vec) | Func::Mul(vec) => {
+                let add = matches!(func, Func::Add(_));
+                let mut acc = self.record(&vec[0], point, vars, dir);
+                for el in &vec[1..] {
+                    let el = self.record(el, point, vars, dir);
+                    acc = self.binary(acc, el, add);
+                }
+                acc
+            }
+            Func::Pow(base, exp) if !has_vars(exp) => {
+                // x^c
+                let exp = exp.eval(point);
+                let base = self.record(base, point, vars, dir);
+                let x = self.vals[base];
+                self.unary(
+                    base,
+                    x.powf(exp),
+                    exp * x.powf(exp - 1.),
+                    exp * (exp - 1.) * x.powf(exp - 2.),
+                )
+            }
+            Func::Pow(base, exp) if !has_vars(base) => {
+                // c^y
+                let ln = base.eval(point).ln();
+                let exp = self.record(exp, point, vars, dir);
+                let val = func.eval(point);
+                self.unary(exp, val, ln * val, ln * ln * val)
+            }
+            Func::Pow(base, exp) => {
+                // e^(y ln(x))
+                let base = self.record(base, point, vars, dir);
+                let x = self.vals[base];
+                let (d1, d2) = FType::Ln.derivatives(x, x.ln());
+                let ln = self.unary(base, x.ln(), d1, d2);
+                let exp = self.record(exp, point, vars, dir);
+                let exponent = self.binary(exp, ln, false);
+                let val = func.eval(point);
+                self.unary(exponent, val, val, val)
+            }
+            Func::S(kind, arg) => {
+                let arg = self.record(arg, point, vars, dir);
+                let x = self.vals[arg];
+                let val = kind.apply(x);
+                let (d1, d2) = kind.derivatives(x, val);
+                self.unary(arg, val, d1, d2)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Backward sweep: gradient, and the hessian-vector product when the tape has a direction
+    fn sweep(&self, vars: usize) -> (Vec<f64>, Vec<f64>) {
+        let len = self.nodes.len();
+        let second = !self.dots.is_empty();
+        let mut adj = vec![0.; len];
+        // Derivatives of the adjoints along the direction
+        let mut adj_dots = vec![0.; if second { len } else { 0 }];
+        let mut grad = vec![0.; vars];
+        let mut hess_dir = vec![0.; if second { vars } else { 0 }];
+        adj[len - 1] = 1.;
+
+        for i in (0..len).rev() {
+            let a = adj[i];
+            let a_dot = adj_dots.get(i).copied().unwrap_or(0.);
+            match self.nodes[i] {
+                Node::Const => {}
+                Node::Var(slot) => {
+                    grad[slot] += a;
+                    if second {
+                        hess_dir[slot] += a_dot;
+                    }
+                }
+                Node::Add(lhs, rhs) => {
+                    adj[lhs] += a;
+                    adj[rhs] += a;
+                    if second {
+                        adj_dots[lhs] += a_dot;
+                        adj_dots[rhs] += a_dot;
+                    }
+                }
+                Node::Mul(lhs, rhs) => {
+                    adj[lhs] += a * self.vals[rhs];
+                    adj[rhs] += a * self.vals[lhs];
+                    if second {
+                        adj_dots[lhs] += a_dot * self.vals[rhs] + a * self.dots[rhs];
+                        adj_dots[rhs] += a_dot * self.vals[lhs] + a * self.dots[lhs];
+                    }
+                }
+                Node::Unary(arg, d1, d2) => {
+                    adj[arg] += a * d1;
+                    if second {
+                        adj_dots[arg] += a_dot * d1 + a * d2 * self.dots[arg];
+                    }
+                }
+            }
+        }
+        (grad, hess_dir)
+    }
+}
+
+impl FND {
+    /// Evaluates the function and its gradient with one forward and one backward sweep
+    /// (reverse mode), the cost doesn't grow with the number of variables.
+    /// The gradient follows the order of the variables
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("a^2b+c", &["a", "b", "c"]);
+    /// let (val, grad) = f.eval_with_gradient(&[("a", 3.), ("b", 2.), ("c", 1.)]);
+    /// assert_eq!((val, grad), (19., vec![12., 9., 1.]));
+    /// ```
+    ///
+    /// # Panics
+    /// If a variable of the function has no value
+    pub fn eval_with_gradient(&self, val: &[(&str, f64)]) -> (f64, Vec<f64>) {
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        let tape = Tape::new(&self.func, val, &vars, None);
+        let (grad, _) = tape.sweep(vars.len());
+        (tape.vals[tape.vals.len() - 1], grad)
+    }
+    /// Evaluates the function, its gradient and the product of its hessian with `dir`,
+    /// without building the hessian. `dir` follows the order of the variables
+    /// ```
+    /// use ruut_functions::{fnd, FND};
+    ///
+    /// let f = fnd!("a^2b", &["a", "b"]);
+    /// let (val, grad, hv) = f.eval_hessian_vector(&[("a", 3.), ("b", 2.)], &[1., -1.]);
+    /// assert_eq!((val, grad), (18., vec![12., 9.]));
+    /// // [[4, 6], [6, 0]] * [1, -1]
+    /// assert_eq!(hv, vec![-2., 6.]);
+    /// ```
+    ///
+    /// # Panics
+    /// If a variable of the function has no value or `dir` doesn't have one component per
+    /// variable
+    pub fn eval_hessian_vector(
+        &self,
+        val: &[(&str, f64)],
+        dir: &[f64],
+    ) -> (f64, Vec<f64>, Vec<f64>) {
+        assert_eq!(dir.len(), self.vars.len(), "One component per variable");
+        let vars: Vec<&str> = self.vars.iter().map(|var| var.as_str()).collect();
+        let tape = Tape::new(&self.func, val, &vars, Some(dir));
+        let (grad, hess_dir) = tape.sweep(vars.len());
+        (tape.vals[tape.vals.len() - 1], grad, hess_dir)
+    }
+}
+
+#[test]
+fn test_reverse() {
+    use crate::fnd;
+
+    let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * (1. + b.abs());
+
+    let vars = ["theta", "r", "v_x"];
+    let funcs = [
+        fnd!("r^2cos(theta)+v_x/r+r^v_x+2^theta+abs(v_x)", &vars),
+        fnd!(
+            "ln(r)sinh(theta)tanh(v_x)+atan(theta/r)+sqrt(r)e^(-v_x)+[a]r",
+            &vars
+        ),
+        fnd!(
+            "asin(theta)acos(theta/2)+sec(r)csc(v_x)cot(theta)+asinh(v_x)+7",
+            &vars
+        ),
+    ];
+    let point = [("theta", 0.3), ("r", 1.7), ("v_x", -2.)];
+    let dir = [0.5, -1.5, 2.];
+    for f in &funcs {
+        let (val, grad, hessian) = f.eval_with_hessian(&point);
+        let (val2, grad2, hv) = f.eval_hessian_vector(&point, &dir);
+        assert_eq!(f.eval_with_gradient(&point), (val2, grad2.clone()));
+        assert!(close(val2, val));
+        for i in 0..3 {
+            assert!(close(grad2[i], grad[i]));
+            let expected: f64 = (0..3).map(|j| hessian[i][j] * dir[j]).sum();
+            assert!(close(hv[i], expected));
+        }
+    }
+
+    // Chain of 200 variables
+    let vars: Vec<String> = (0..200).map(|i| format!("x_{}", i)).collect();
+    let terms: Vec<String> = (0..199)
+        .map(|i| format!("(x_{}-x_{})^2+sin(x_{})", i, i + 1, i))
+        .collect();
+    let vars_ref: Vec<&str> = vars.iter().map(|var| var.as_str()).collect();
+    let f = FND::new(&terms.join("+"), &vars_ref).unwrap();
+    let point: Vec<(&str, f64)> = vars_ref
+        .iter()
+        .enumerate()
+        .map(|(i, var)| (*var, (i as f64 * 0.37).cos()))
+        .collect();
+    let (val, grad) = f.eval_with_gradient(&point);
+    assert!(close(val, f.eval(&point)));
+    for (i, der) in grad.iter().enumerate() {
+        let x = point[i].1;
+        let mut expected = 0.;
+        if i > 0 {
+            expected += 2. * (x - point[i - 1].1);
+        }
+        if i < 199 {
+            expected += 2. * (x - point[i + 1].1) + x.cos();
+        }
+        assert!(close(*der, expected));
+    }
+}
@@ -1,11 +1,22 @@
-use std::iter::OnceWith;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive};
 
-use crate::{gcd, FType, Func};
+use crate::{FType, Func};
 
 pub(crate) fn simp_node(func: &mut Func) {
     unwrap_par(func);
 
     match func {
+        Func::Pow(base, exp) if fold_pow(base, exp).is_some() => {
+            if let Some(folded) = fold_pow(base, exp) {
+                *func = folded;
+            }
+        }
+        Func::S(kind, arg) if fold_special(*kind, arg).is_some() => {
+            if let Some(folded) = fold_special(*kind, arg) {
+                *func = folded;
+            }
+        }
         Func::Add(add) => {
             add.sort_unstable();
             if simp_add(add) {
@@ -31,8 +42,10 @@ pub(crate) fn simp_node(func: &mut Func) {
                 if let Func::Pow(base_e, exp_e) = &**exp {
                     if let Func::S(FType::Ln, arg) = &**base_e {
                         if let Func::Num(val) = &**exp_e {
-                            *func = (arg.clone())
-                                .pow(Func::S(FType::Ln, Box::new(arg.clone().powi(val - 1))));
+                            *func = (arg.clone()).pow(Func::S(
+                                FType::Ln,
+                                Box::new(arg.clone().pow(Func::Num(*val) - 1)),
+                            ));
                         }
                     }
                 }
@@ -53,9 +66,9 @@ pub(crate) fn simp_node(func: &mut Func) {
         Func::S(FType::Cos, arg) => {
             if let Func::Mul(mul_vec) = &mut **arg {
                 if mul_vec.len() > 1 {
-                    if let Func::Num(val) = mul_vec[0] {
-                        if val < 0 {
-                            mul_vec[0] = Func::Num(-val);
+                    if let Some(val) = as_rational(&mul_vec[0]) {
+                        if val.is_negative() {
+                            mul_vec[0] = from_rational(-val);
                             simp_node(arg);
                         }
                     }
@@ -89,30 +102,16 @@ fn simp_add(add: &mut Vec<Func>) -> bool {
 
         for second in others.iter_mut() {
             let new_func = match (&firsts[i], &*second) {
-                (Func::Num(add1), Func::Num(add2)) if *add1 != 0 && *add2 != 0 => {
-                    Some(Func::Num(add1 + add2))
+                (
+                    add1 @ (Func::Num(_) | Func::Rational(_)),
+                    add2 @ (Func::Num(_) | Func::Rational(_)),
+                ) if *add1 != 0 && *add2 != 0 => fold_consts(add1, add2, |a, b| a + b),
+                (Func::Float(add1), other) | (other, Func::Float(add1)) if *other != 0 => {
+                    float_value(other).and_then(|add2| finite(add1 + add2))
                 }
                 (Func::S(FType::Ln, arg1), Func::S(FType::Ln, arg2)) => {
                     Some(Func::S(FType::Ln, Box::new(*arg1.clone() * *arg2.clone())))
                 }
-                (Func::Mul(lhs), Func::Mul(rhs)) if !has_div(lhs) && !has_div(rhs) => {
-                    // 2x+x = 3x
-                    let mut lhs_c = (0, 1); // (index + 1) and value of coefficient
-                    let mut rhs_c = (0, 1);
-
-                    if let Func::Num(val) = lhs[0] {
-                        lhs_c = (1, val);
-                    }
-                    if let Func::Num(val) = rhs[0] {
-                        rhs_c = (1, val);
-                    }
-
-                    if lhs[lhs_c.0..lhs.len()] == rhs[rhs_c.0..rhs.len()] {
-                        Some((lhs_c.1 + rhs_c.1) * Func::Mul(lhs[lhs_c.0..lhs.len()].to_vec()))
-                    } else {
-                        None
-                    }
-                }
                 (Func::Pow(base, exp), Func::Pow(base2, exp2)) if **exp == 2 && **exp2 == 2 => {
                     match (&**base, &**base2) {
                         (Func::S(FType::Sin, arg1), Func::S(FType::Cos, arg2))
@@ -135,21 +134,12 @@ fn simp_add(add: &mut Vec<Func>) -> bool {
                     }
                     result
                 }
-                (_, _) => None,
+                (lhs, rhs) => like_terms(lhs, rhs),
             };
             if let Some(f) = new_func {
                 *second = f;
                 firsts[i] = Func::Num(0);
                 worked = true;
-            } else if let Some(a) = is_rational(&firsts[i]) {
-                if let Some(b) = is_rational(second) {
-                    let num = a.0 * b.1 + b.0 * a.1;
-                    let den = a.1 * b.1;
-                    let gcd = gcd(num as u32, den as u32) as i32;
-                    *second = Func::Mul(vec![Func::Num(num / gcd), Func::Num(den / gcd).powi(-1)]);
-                    firsts[i] = Func::Num(0);
-                    worked = true;
-                }
             }
         }
     }
@@ -162,11 +152,60 @@ fn simp_add(add: &mut Vec<Func>) -> bool {
     worked
 }
 
+// 2x+x = 3x, constants and quotients aside
+fn like_terms(lhs: &Func, rhs: &Func) -> Option<Func> {
+    for term in [lhs, rhs] {
+        match term {
+            Func::Mul(mul) if has_div(mul) => return None,
+            Func::Num(_) | Func::Rational(_) | Func::Float(_) => return None,
+            _ => (),
+        }
+    }
+    merge_terms(lhs, rhs, |factor| {
+        matches!(factor, Func::Num(_) | Func::Rational(_))
+    })
+}
+
+// Sum of two terms made of the same factors besides those of their coefficients, the leading
+// ones for which is_coef holds: 2x+3x = 5x, or with the factors free of x, yx+2x = (2+y)x
+pub(crate) fn merge_terms(lhs: &Func, rhs: &Func, is_coef: impl Fn(&Func) -> bool) -> Option<Func> {
+    let (lhs_c, lhs) = split_coefficient(lhs, &is_coef);
+    let (rhs_c, rhs) = split_coefficient(rhs, &is_coef);
+    if lhs != rhs {
+        return None;
+    }
+    Some((from_factors(lhs_c) + from_factors(rhs_c)) * from_factors(lhs))
+}
+
+// Leading factors of a term for which is_coef holds and the others
+pub(crate) fn split_coefficient(
+    func: &Func,
+    is_coef: impl Fn(&Func) -> bool,
+) -> (&[Func], &[Func]) {
+    let factors = match func {
+        Func::Mul(mul) => mul.as_slice(),
+        _ => std::slice::from_ref(func),
+    };
+    let mut coef = 0;
+    while coef < factors.len() && is_coef(&factors[coef]) {
+        coef += 1;
+    }
+    factors.split_at(coef)
+}
+
+pub(crate) fn from_factors(factors: &[Func]) -> Func {
+    match factors {
+        [] => Func::Num(1),
+        [factor] => factor.clone(),
+        _ => Func::Mul(factors.to_vec()),
+    }
+}
+
 fn simp_mul(mul: &mut Vec<Func>) -> bool {
     let mut worked = false;
+    // Factors are compared in their simplified form: 1/y^2 is y^-2 next to y
+    mul.iter_mut().for_each(simp_node);
     for i in 0..mul.len() {
-        simp_node(&mut mul[i]);
-
         if let Func::Pow(base, _) = &mul[i] {
             if **base == 1 {
                 mul[i] = Func::Num(1);
@@ -176,8 +215,15 @@ fn simp_mul(mul: &mut Vec<Func>) -> bool {
 
         for second in others.iter_mut() {
             let new_func = match (&firsts[i], &second) {
-                (Func::Num(mul1), Func::Num(mul2)) if *mul2 != 1 && *mul1 != 1 => {
-                    Some(Func::Num(mul1 * mul2))
+                (
+                    mul1 @ (Func::Num(_) | Func::Rational(_)),
+                    mul2 @ (Func::Num(_) | Func::Rational(_)),
+                ) if **mul2 != 1 && *mul1 != 1 => fold_consts(mul1, mul2, |a, b| a * b),
+                (Func::Float(mul1), other) if **other != 1 => {
+                    float_value(other).and_then(|mul2| finite(mul1 * mul2))
+                }
+                (other, Func::Float(mul1)) if *other != 1 => {
+                    float_value(other).and_then(|mul2| finite(mul1 * mul2))
                 }
                 (Func::S(kind, arg1), Func::S(kind2, arg2)) if arg1 == arg2 => {
                     match (kind, kind2) {
@@ -199,24 +245,25 @@ fn simp_mul(mul: &mut Vec<Func>) -> bool {
                 (Func::S(kind, arg1), Func::Pow(base, exp)) => {
                     let mut result = None;
                     if let Func::Num(exp) = **exp {
+                        let exp = 1 + Func::Num(exp);
                         if let Func::S(kind2, arg2) = &**base {
                             if arg1 == arg2 {
                                 result = match (kind, kind2) {
                                     (FType::Sin, FType::Cos) => Some(
                                         Func::S(FType::Tan, arg1.clone())
-                                            * base.clone().powi(exp + 1),
+                                            * base.clone().pow(exp.clone()),
                                     ),
                                     (FType::Cos, FType::Sin) => Some(
                                         Func::S(FType::Cot, arg1.clone())
-                                            * base.clone().powi(exp + 1),
+                                            * base.clone().pow(exp.clone()),
                                     ),
                                     (FType::Tan, FType::Sin) => Some(
                                         Func::S(FType::Sec, arg1.clone())
-                                            * base.clone().powi(exp + 1),
+                                            * base.clone().pow(exp.clone()),
                                     ),
                                     (FType::Cot, FType::Cos) => Some(
                                         Func::S(FType::Csc, arg1.clone())
-                                            * base.clone().powi(exp + 1),
+                                            * base.clone().pow(exp.clone()),
                                     ),
                                     (_, _) => None,
                                 };
@@ -225,6 +272,12 @@ fn simp_mul(mul: &mut Vec<Func>) -> bool {
                     }
                     result
                 }
+                // sin(x)^n/cos(x)^n -> tan(x)^n
+                (Func::Pow(base1, exp1), Func::Pow(base2, exp2))
+                    if **exp1 == -1 * *exp2.clone() && sin_cos(base1, base2).is_some() =>
+                {
+                    sin_cos(base1, base2).map(|tan| tan.pow(*exp1.clone()))
+                }
                 (Func::Pow(base1, exp1), Func::Pow(base2, exp2)) if **base1 == **base2 => {
                     Some(base1.clone().pow(*exp1.clone() + *exp2.clone()))
                 }
@@ -286,43 +339,127 @@ fn remove_neutral(funcs: &mut Vec<Func>, neutral: Func) -> bool {
     worked
 }
 
-pub(crate) fn is_rational(func: &Func) -> Option<(i32, i32)> {
-    if let Func::Num(val) = func {
-        return Some((*val, 1));
+// Exact value of a numeric constant
+pub(crate) fn as_rational(func: &Func) -> Option<BigRational> {
+    match func {
+        Func::Num(val) => Some(BigRational::from_integer((*val).into())),
+        Func::Rational(val) => Some(val.clone()),
+        _ => None,
     }
+}
 
-    if let Func::Mul(mul) = func {
-        if mul.len() == 2 {
-            if let Func::Num(num) = mul[0] {
-                if let Func::Pow(base, exp) = &mul[1] {
-                    if let Func::Num(den) = **base {
-                        if **exp == -1 {
-                            let gcd = gcd(num.unsigned_abs(), den.unsigned_abs());
-                            return Some((num / gcd as i32, den / gcd as i32));
-                        }
-                    }
-                }
-            }
+// Canonical node for a constant: Num when it's an integer that fits in an i32
+pub(crate) fn from_rational(val: BigRational) -> Func {
+    if val.is_integer() {
+        if let Some(int) = val.to_integer().to_i32() {
+            return Func::Num(int);
         }
     }
+    Func::Rational(val)
+}
 
-    if let Func::Pow(base, exp) = func {
-        if let Func::Num(den) = **base {
-            if **exp == -1 {
-                return Some((1, den));
-            }
+// Folds an operation between two numeric constants
+pub(crate) fn fold_consts(
+    lhs: &Func,
+    rhs: &Func,
+    op: impl Fn(BigRational, BigRational) -> BigRational,
+) -> Option<Func> {
+    Some(from_rational(op(as_rational(lhs)?, as_rational(rhs)?)))
+}
+
+// Bits a folded power is allowed to take, bigger ones stay as Pow
+const MAX_FOLD_BITS: u64 = 4096;
+
+// base^exp for a numeric base and an integer exponent, or any numeric base and exponent
+// when one of them is a float
+pub(crate) fn fold_pow(base: &Func, exp: &Func) -> Option<Func> {
+    if matches!(base, Func::Float(_)) || matches!(exp, Func::Float(_)) {
+        return finite(float_value(base)?.powf(float_value(exp)?));
+    }
+    let base = as_rational(base)?;
+    let Func::Num(exp) = exp else {
+        return None;
+    };
+    if *exp == 1 || (base == BigRational::from_integer(0.into()) && *exp < 0) {
+        return None;
+    }
+    let bits = base.numer().bits().max(base.denom().bits());
+    if bits.saturating_mul(exp.unsigned_abs() as u64) > MAX_FOLD_BITS {
+        return None;
+    }
+    Some(from_rational(base.pow(*exp)))
+}
+
+// Exact when the value is an i32 integer
+pub(crate) fn from_float(val: f64) -> Func {
+    if val.fract() == 0. && val.abs() <= i32::MAX as f64 {
+        Func::Num(val as i32)
+    } else {
+        Func::Float(val)
+    }
+}
+
+// Exact values at special points: sin(0) = 0, cos(pi) = -1, ln(1) = 0, ...
+pub(crate) fn fold_special(kind: FType, arg: &Func) -> Option<Func> {
+    let zero = Some(Func::Num(0));
+    let one = Some(Func::Num(1));
+    if *arg == 0 {
+        return match kind {
+            FType::Sin | FType::Tan | FType::ASin | FType::ATan => zero,
+            FType::Sinh | FType::Tanh | FType::ASinh | FType::ATanh | FType::Abs => zero,
+            FType::Cos | FType::Sec | FType::Cosh | FType::Sech => one,
+            FType::ACos => Some(Func::PI / 2),
+            _ => None,
+        };
+    }
+    if *arg == 1 {
+        return match kind {
+            FType::Ln | FType::ACos | FType::ACosh => zero,
+            FType::ASin => Some(Func::PI / 2),
+            FType::ATan => Some(Func::PI / 4),
+            FType::Abs => one,
+            _ => None,
+        };
+    }
+    match (kind, arg) {
+        (FType::Ln, Func::E) => one,
+        (FType::Sin | FType::Tan, Func::PI) => zero,
+        (FType::Cos | FType::Sec, Func::PI) => Some(Func::Num(-1)),
+        (FType::Abs, _) => as_rational(arg).map(|val| from_rational(val.abs())),
+        _ => None,
+    }
+}
+
+// tan(x) for sin(x)/cos(x), cot(x) for cos(x)/sin(x)
+fn sin_cos(lhs: &Func, rhs: &Func) -> Option<Func> {
+    match (lhs, rhs) {
+        (Func::S(FType::Sin, arg1), Func::S(FType::Cos, arg2)) if arg1 == arg2 => {
+            Some(Func::S(FType::Tan, arg1.clone()))
+        }
+        (Func::S(FType::Cos, arg1), Func::S(FType::Sin, arg2)) if arg1 == arg2 => {
+            Some(Func::S(FType::Cot, arg1.clone()))
         }
+        _ => None,
     }
+}
 
-    None
+// Folded floats stay finite: inf and NaN are left to the evaluation
+fn finite(val: f64) -> Option<Func> {
+    val.is_finite().then_some(Func::Float(val))
+}
+
+// Value of a numeric constant that can be folded into a float
+fn float_value(func: &Func) -> Option<f64> {
+    if let Func::Float(val) = func {
+        return Some(*val);
+    }
+    as_rational(func).and_then(|val| val.to_f64())
 }
 
 fn unwrap_par(func: &mut Func) {
     match func {
-        Func::Add(vec) | Func::Mul(vec) => {
-            if vec.len() == 1 {
-                *func = vec[0].clone();
-            }
+        Func::Add(vec) | Func::Mul(vec) if vec.len() == 1 => {
+            *func = vec[0].clone();
         }
         _ => (),
     }
@@ -337,6 +474,10 @@ fn test_simp() {
         f1d!("3x+2x+cot(x^2)sin(x^2)+cot(x)*tan(x)"),
         f1d!("5x+1+cos(x^2)")
     );
+    assert_eq!(f1d!("cos(x)-cos(x)"), f1d!("0"));
+    assert_eq!(f1d!("sin(0)+cos(pi)+ln(e)+abs(-3/2)x"), f1d!("3x/2"));
+    assert_eq!(f1d!("acos(0)+atan(1)"), f1d!("3pi/4"));
+    assert_eq!(f1d!("3sin(x)+sin(x)+x+x"), f1d!("2x+4sin(x)"));
 
     assert_eq!(
         f1d!("sin(x)/cos(x)+1/2-7+sin(x)/cos(2x)+(x^2)^3+ln(e^2)"),
@@ -354,5 +495,36 @@ fn test_simp() {
     assert_eq!(f1d!("e^(ln(x)^2)"), f1d!("x^ln(x)"));
 
     assert_eq!(f1d!("sin(x)/cos(x)^2"), f1d!("tan(x)/cos(x)"));
-    assert_eq!(f1d!("sin(x)^2/cos(x)^2"), f1d!("tan(x)^2"))
+    assert_eq!(f1d!("sin(x)^2/cos(x)^2"), f1d!("tan(x)^2"));
+    // Opposite powers of the same base cancel
+    assert_eq!(f1d!("x^2*x^(-2)"), f1d!("1"));
+    assert_eq!(f1d!("x^(1/2)*x^(-1/2)"), f1d!("1"));
+    assert_eq!(f1d!("e^x*e^(-x)"), f1d!("1"));
+
+    assert_eq!(f1d!("0.5x+0.25x^2*2"), f1d!("0.5x+0.5x^2"));
+    assert_eq!(f1d!("0.5+1/4+1"), f1d!("1.75"));
+    assert_eq!(f1d!("2.5*4*x"), f1d!("10.0x"));
+    assert_eq!(f1d!("0.5^2"), f1d!("0.25"));
+    assert_eq!(format!("{}", f1d!("x*0.5^2")), "0.25x");
+    assert_eq!(format!("{}", f1d!("1e308*10x")), "1e308*10x");
+
+    assert_eq!(f1d!("1/2-1/2"), f1d!("0"));
+    assert_eq!(f1d!("x/2-x/2+1/3"), f1d!("1/3"));
+    assert_eq!(f1d!("1/3+1/6"), f1d!("1/2"));
+    assert_eq!(f1d!("100000*100000*100000/1000000000000000"), f1d!("1"));
+    assert_eq!(format!("{}", f1d!("2^40x")), "1099511627776x");
+    assert_eq!(format!("{}", f1d!("(2/3)^2-x/7")), "4/9-x/7");
+    assert_eq!(format!("{}", f1d!("3sin(x)/(7x)")), "3sin(x)/(7x)");
+
+    // Factors are simplified before they are compared: (y^2)^-1 meets y as y^-2
+    let y = || Func::Var("y".to_string());
+    let mut func = Func::Mul(vec![
+        y(),
+        Func::Pow(
+            Box::new(Func::Pow(Box::new(y()), Box::new(Func::Num(2)))),
+            Box::new(Func::Num(-1)),
+        ),
+    ]);
+    simp_node(&mut func);
+    assert_eq!(func, Func::Pow(Box::new(y()), Box::new(Func::Num(-1))));
 }
@@ -0,0 +1,189 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+
+use crate::{
+    simp::{fold_special, from_float, from_rational, simp_node},
+    Func, Interval, F1D, F2D, F3D, FND,
+};
+
+// Exact centre from the shortest decimal that prints the float: 0.1 is 1/10
+fn decimal(val: f64) -> Option<BigRational> {
+    if !val.is_finite() {
+        return None;
+    }
+    let literal = format!("{}", val);
+    let (int, frac) = literal.split_once('.').unwrap_or((&literal, ""));
+    let numer: BigInt = format!("{}{}", int, frac).parse().ok()?;
+    Some(BigRational::new(
+        numer,
+        BigInt::from(10).pow(frac.len() as u32),
+    ))
+}
+
+impl Func {
+    // Replaces variables with values, rebuilding through the ops so that constants fold exactly
+    pub(crate) fn substitute(&self, vals: &[(&str, Func)]) -> Self {
+        match self {
+            Func::Var(name) => vals
+                .iter()
+                .find(|(var, _)| var == name)
+                .map_or_else(|| self.clone(), |(_, val)| val.clone()),
+            Func::Add(add) => add.iter().map(|term| term.substitute(vals)).sum(),
+            Func::Mul(mul) => mul.iter().map(|term| term.substitute(vals)).product(),
+            Func::Pow(base, exp) => base.substitute(vals).pow(exp.substitute(vals)),
+            Func::S(kind, arg) => {
+                let arg = arg.substitute(vals);
+                fold_special(*kind, &arg).unwrap_or_else(|| Func::S(*kind, Box::new(arg)))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    // Sum over the partials of d^a f(p) / a! (x - p)^a
+    fn taylor(&self, point: &[(&str, f64)], order: usize) -> Self {
+        let vars: Vec<&str> = point.iter().map(|(var, _)| *var).collect();
+        let vals: Vec<(&str, Func)> = point
+            .iter()
+            .map(|(var, val)| {
+                (
+                    *var,
+                    decimal(*val).map_or_else(|| from_float(*val), from_rational),
+                )
+            })
+            .collect();
+        let shifts: Vec<Func> = vals
+            .iter()
+            .map(|(var, val)| Func::Var(var.to_string()) - val.clone())
+            .collect();
+
+        let mut result = Func::Num(0);
+        for (index, der) in self.partials(&vars, order) {
+            let coef = der.substitute(&vals);
+            if coef == 0 {
+                continue;
+            }
+            let factorial: BigInt = index
+                .iter()
+                .flat_map(|&n| 1..=n)
+                .map(BigInt::from)
+                .product();
+            let mut term = coef / from_rational(BigRational::from_integer(factorial));
+            for (shift, &n) in shifts.iter().zip(&index) {
+                term *= shift.clone().powi(n as i32);
+            }
+            result += term;
+        }
+        simp_node(&mut result);
+        result
+    }
+}
+
+impl F1D {
+    /// Computes the Taylor polynomial of degree `order` around x0. The centre is taken as the
+    /// decimal it prints as, so coefficients are exact rationals: 0.1 is 1/10
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// assert_eq!(f1d!("sin(x)").taylor(0., 5), f1d!("x-x^3/6+x^5/120"));
+    /// assert_eq!(f1d!("e^x").taylor(0., 3), f1d!("1+x+x^2/2+x^3/6"));
+    /// assert_eq!(f1d!("ln(x)").taylor(1., 2), f1d!("x-1-(x-1)^2/2"));
+    /// ```
+    pub fn taylor(&self, x0: f64, order: usize) -> Self {
+        F1D(self.0.taylor(&[("x", x0)], order))
+    }
+
+    /// Bounds the error of the Taylor polynomial of degree `order` around x0 at x, with the
+    /// Lagrange remainder: max|f^(order+1)| between x0 and x times |x-x0|^(order+1)/(order+1)!
+    /// ```
+    /// use ruut_functions::{f1d, F1D};
+    ///
+    /// let f = f1d!("sin(x)");
+    /// let error = (f.eval(0.5) - f.taylor(0., 3).eval(0.5)).abs();
+    /// assert!(error <= f.taylor_remainder(0., 3, 0.5));
+    /// ```
+    pub fn taylor_remainder(&self, x0: f64, order: usize, x: f64) -> f64 {
+        let der = self.derive(order + 1);
+        let range = der.eval_interval(Interval::new(x0.min(x), x0.max(x)));
+        let max = range.lo.abs().max(range.hi.abs());
+        let factorial: f64 = (1..=order + 1).map(|n| n as f64).product();
+        max * (x - x0).abs().powi(order as i32 + 1) / factorial
+    }
+}
+impl F2D {
+    /// Computes the Taylor polynomial of total degree `order` around (x0,y0)
+    /// ```
+    /// use ruut_functions::{f2d, F2D};
+    ///
+    /// assert_eq!(f2d!("e^x cos(y)").taylor(0., 0., 2), f2d!("1+x+x^2/2-y^2/2"));
+    /// ```
+    pub fn taylor(&self, x0: f64, y0: f64, order: usize) -> Self {
+        F2D(self.0.taylor(&[("x", x0), ("y", y0)], order))
+    }
+}
+impl F3D {
+    /// Computes the Taylor polynomial of total degree `order` around (x0,y0,z0)
+    pub fn taylor(&self, x0: f64, y0: f64, z0: f64, order: usize) -> Self {
+        F3D(self.0.taylor(&[("x", x0), ("y", y0), ("z", z0)], order))
+    }
+}
+impl FND {
+    /// Computes the Taylor polynomial of total degree `order` around a point, variables
+    /// without a value are treated as constants
+    pub fn taylor(&self, point: &[(&str, f64)], order: usize) -> Self {
+        let point: Vec<(&str, f64)> = self
+            .vars
+            .iter()
+            .filter_map(|var| point.iter().find(|(name, _)| name == var).copied())
+            .collect();
+        FND {
+            vars: self.vars.clone(),
+            func: self.func.taylor(&point, order),
+        }
+    }
+}
+
+#[test]
+fn test_taylor() {
+    use crate::{f1d, f2d, f3d, fnd};
+
+    assert_eq!(f1d!("cos(x)").taylor(0., 4), f1d!("1-x^2/2+x^4/24"));
+    assert_eq!(f1d!("1/(1-x)").taylor(0., 3), f1d!("1+x+x^2+x^3"));
+    assert_eq!(f1d!("atan(x)").taylor(0., 3), f1d!("x-x^3/3"));
+    assert_eq!(f1d!("x^3").taylor(2., 1), f1d!("8+12(x-2)"));
+    assert_eq!(f1d!("sin(x)").taylor(0., 0), f1d!("0"));
+    // Exact even past i32 factorials
+    let f = f1d!("e^x").taylor(0., 14);
+    assert_eq!(f.derive(14), f1d!("1"));
+
+    // Symbolic constants stay exact
+    assert_eq!(f1d!("sin(x)").taylor(1., 1), f1d!("sin(1)+cos(1)(x-1)"));
+    // Non-integer centres give exact coefficients
+    assert_eq!(f1d!("x^3").taylor(0.5, 1), f1d!("1/8+3(x-1/2)/4"));
+    assert_eq!(f1d!("1/x").taylor(0.25, 1), f1d!("4-16(x-1/4)"));
+    assert_eq!(f1d!("x^2").taylor(0.1, 1), f1d!("1/100+(x-1/10)/5"));
+    assert_eq!(f1d!("x").taylor(-2.5e-3, 0), f1d!("-1/400"));
+
+    for order in [1, 3, 6] {
+        let f = f1d!("e^(x/2)sin(x)");
+        let poly = f.taylor(0.5, order);
+        for x in [0., 0.3, 0.9, 1.4] {
+            let error = (f.eval(x) - poly.eval(x)).abs();
+            assert!(error <= f.taylor_remainder(0.5, order, x) + 1e-12);
+        }
+    }
+
+    assert_eq!(
+        f2d!("ln(1+x+y)").taylor(0., 0., 2),
+        f2d!("x+y-x^2/2-xy-y^2/2")
+    );
+    let f = f3d!("xyz+sin(x)e^(y)z");
+    let poly = f.taylor(0.1, -0.2, 0.3, 3);
+    let (x, y, z) = (0.12, -0.19, 0.31);
+    assert!((poly.eval(x, y, z) - f.eval(x, y, z)).abs() < 1e-6);
+
+    let vars = ["v", "w"];
+    assert_eq!(
+        fnd!("v^2w+w", &vars).taylor(&[("v", 1.), ("w", 0.)], 1),
+        fnd!("2w", &vars)
+    );
+}
@@ -1,4 +1,7 @@
-use crate::{FType, Func, F1D, F2D, F3D};
+use num_rational::BigRational;
+use num_traits::Signed;
+
+use crate::{vector::MismatchedVars, FType, FVec, Func, F1D, F2D, F3D, FND};
 
 impl PartialEq<i32> for Func {
     fn eq(&self, other: &i32) -> bool {
@@ -11,10 +14,10 @@ impl PartialEq<i32> for Func {
 }
 impl PartialOrd<i32> for Func {
     fn partial_cmp(&self, other: &i32) -> Option<std::cmp::Ordering> {
-        if let Func::Num(val) = self {
-            Some(val.cmp(other))
-        } else {
-            None
+        match self {
+            Func::Num(val) => Some(val.cmp(other)),
+            Func::Rational(val) => val.partial_cmp(&BigRational::from_integer((*other).into())),
+            _ => None,
         }
     }
 }
@@ -39,17 +42,27 @@ impl Ord for Func {
             }
         }
 
+        if let (Func::Var(name1), Func::Var(name2)) = (self, other) {
+            // Same initial: fall back on the whole name
+            return func_order(self)
+                .cmp(&func_order(other))
+                .then_with(|| name1.cmp(name2));
+        }
+
         func_order(self).cmp(&func_order(other))
     }
 }
 
 fn func_order(func: &Func) -> u32 {
     match func {
-        Func::Num(_) => 0,
+        Func::Num(_) | Func::Float(_) | Func::Rational(_) => 0,
         Func::PI => 1,
         Func::E => 2,
         Func::Param(..) => 3,
-        Func::Var(char) => char.to_ascii_lowercase() as u32,
+        Func::Var(name) => name
+            .chars()
+            .next()
+            .map_or(0, |char| char.to_ascii_lowercase() as u32),
         Func::Mul(_) => 123,
         Func::Add(_) => 124,
         Func::S(kind, _) => match kind {
@@ -74,17 +87,12 @@ fn func_order(func: &Func) -> u32 {
             FType::ACosh => 144,
             FType::ATanh => 145,
         },
-        Func::Pow(_, exp) => {
-            if let Func::Num(val) = **exp {
-                if val < 0 {
-                    147
-                } else {
-                    146
-                }
-            } else {
-                146
-            }
-        }
+        Func::Pow(_, exp) => match **exp {
+            Func::Num(val) if val < 0 => 147,
+            Func::Float(val) if val < 0. => 147,
+            Func::Rational(ref val) if val.is_negative() => 147,
+            _ => 146,
+        },
     }
 }
 
@@ -115,3 +123,36 @@ impl From<F1D> for F2D {
         F2D(value.0)
     }
 }
+impl From<Vec<F1D>> for FVec {
+    fn from(value: Vec<F1D>) -> Self {
+        FVec::from_funcs(value.into_iter().map(|f| f.0).collect(), &["x"])
+    }
+}
+impl From<Vec<F2D>> for FVec {
+    fn from(value: Vec<F2D>) -> Self {
+        FVec::from_funcs(value.into_iter().map(|f| f.0).collect(), &["x", "y"])
+    }
+}
+impl From<Vec<F3D>> for FVec {
+    fn from(value: Vec<F3D>) -> Self {
+        FVec::from_funcs(value.into_iter().map(|f| f.0).collect(), &["x", "y", "z"])
+    }
+}
+impl TryFrom<Vec<FND>> for FVec {
+    type Error = MismatchedVars;
+
+    fn try_from(value: Vec<FND>) -> Result<Self, Self::Error> {
+        let vars = value.first().map_or(Vec::new(), |f| f.vars.clone());
+        if let Some(f) = value.iter().find(|f| f.vars != vars) {
+            return Err(MismatchedVars {
+                expected: vars,
+                found: f.vars.clone(),
+            });
+        }
+        let vars: Vec<&str> = vars.iter().map(|var| var.as_str()).collect();
+        Ok(FVec::from_funcs(
+            value.into_iter().map(|f| f.func).collect(),
+            &vars,
+        ))
+    }
+}
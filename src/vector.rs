@@ -0,0 +1,230 @@
+use std::fmt::Display;
+
+use crate::{parser::parse, simp::simp_node, Func, ParsingError, FND};
+
+#[derive(Debug, PartialEq, Clone)]
+/// Error returned when building an `FVec` from components with different variables
+/// ```
+/// use ruut_functions::{fnd, FVec, FND};
+///
+/// let err = FVec::try_from(vec![fnd!("a", &["a"]), fnd!("ab", &["a", "b"])]).unwrap_err();
+/// assert_eq!(format!("{}", err), "component with variables [\"a\", \"b\"], expected [\"a\"]");
+/// ```
+pub struct MismatchedVars {
+    /// Variables of the first component
+    pub expected: Vec<String>,
+    /// Variables of the first component that differs
+    pub found: Vec<String>,
+}
+
+impl Display for MismatchedVars {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "component with variables {:?}, expected {:?}",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for MismatchedVars {}
+
+#[derive(Debug, PartialEq)]
+/// Representation of a vector-valued function, its components share the variables
+/// ```
+/// use ruut_functions::FVec;
+///
+/// let polar = FVec::new(&["r cos(theta)", "r sin(theta)"], &["r", "theta"]).unwrap();
+/// assert_eq!(polar.eval(&[("r", 2.), ("theta", 0.)]), vec![2., 0.]);
+/// // r cos(theta)^2 + r sin(theta)^2
+/// assert_eq!(polar.jacobian_det().eval(&[("r", 2.), ("theta", 0.)]), 2.);
+/// ```
+pub struct FVec {
+    vars: Vec<String>,
+    funcs: Vec<Func>,
+}
+
+impl FVec {
+    /// Creates a new function from one string per component
+    pub fn new(inputs: &[&str], vars: &[&str]) -> Result<Self, ParsingError> {
+        let mut funcs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let mut func = parse(input, vars)?;
+            simp_node(&mut func);
+            funcs.push(func);
+        }
+        Ok(FVec {
+            vars: vars.iter().map(|var| var.to_string()).collect(),
+            funcs,
+        })
+    }
+
+    pub(crate) fn from_funcs(funcs: Vec<Func>, vars: &[&str]) -> Self {
+        FVec {
+            vars: vars.iter().map(|var| var.to_string()).collect(),
+            funcs,
+        }
+    }
+
+    /// Number of components
+    pub fn len(&self) -> usize {
+        self.funcs.len()
+    }
+
+    /// True if the function has no components
+    pub fn is_empty(&self) -> bool {
+        self.funcs.is_empty()
+    }
+
+    /// Variables of the function, in order
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    /// Returns the i-th component
+    pub fn component(&self, i: usize) -> FND {
+        self.fnd(self.funcs[i].clone())
+    }
+
+    /// Evaluates every component
+    ///
+    /// # Panics
+    /// If a variable of a component has no value
+    pub fn eval(&self, val: &[(&str, f64)]) -> Vec<f64> {
+        self.funcs.iter().map(|func| func.eval(val)).collect()
+    }
+
+    /// Computes the jacobian matrix, one row per component and one column per variable
+    /// ```
+    /// use ruut_functions::{FVec, FND, fnd};
+    ///
+    /// let vars = ["u", "v"];
+    /// let f = FVec::new(&["u^2v", "u+sin(v)"], &vars).unwrap();
+    /// assert_eq!(
+    ///     f.jacobian(),
+    ///     vec![
+    ///         vec![fnd!("2uv", &vars), fnd!("u^2", &vars)],
+    ///         vec![fnd!("1", &vars), fnd!("cos(v)", &vars)]
+    ///     ]
+    /// );
+    /// ```
+    pub fn jacobian(&self) -> Vec<Vec<FND>> {
+        self.derivatives()
+            .into_iter()
+            .map(|row| row.into_iter().map(|func| self.fnd(func)).collect())
+            .collect()
+    }
+
+    /// Evaluates the jacobian matrix
+    pub fn eval_jacobian(&self, val: &[(&str, f64)]) -> Vec<Vec<f64>> {
+        self.funcs
+            .iter()
+            .map(|func| self.fnd(func.clone()).eval_with_gradient(val).1)
+            .collect()
+    }
+
+    /// Computes the determinant of the jacobian, by cofactor expansion.
+    /// Panics if the number of components differs from the number of variables
+    pub fn jacobian_det(&self) -> FND {
+        assert_eq!(
+            self.funcs.len(),
+            self.vars.len(),
+            "The jacobian must be square"
+        );
+        let matrix = self.derivatives();
+        let cols: Vec<usize> = (0..matrix.len()).collect();
+        self.fnd(det(&matrix, 0, &cols))
+    }
+
+    fn derivatives(&self) -> Vec<Vec<Func>> {
+        self.funcs
+            .iter()
+            .map(|func| {
+                self.vars
+                    .iter()
+                    .map(|var| func.derive_nth(var, 1))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn fnd(&self, func: Func) -> FND {
+        FND {
+            vars: self.vars.clone(),
+            func,
+        }
+    }
+}
+
+// Determinant of the minor made of the rows from row on and of the columns in cols
+fn det(matrix: &[Vec<Func>], row: usize, cols: &[usize]) -> Func {
+    if cols.is_empty() {
+        return Func::Num(1);
+    }
+    let mut result = Func::Num(0);
+    for (i, &col) in cols.iter().enumerate() {
+        if matrix[row][col] == 0 {
+            continue;
+        }
+        let minor: Vec<usize> = cols.iter().copied().filter(|&c| c != col).collect();
+        let term = matrix[row][col].clone() * det(matrix, row + 1, &minor);
+        if i % 2 == 0 {
+            result += term;
+        } else {
+            result -= term;
+        }
+    }
+    result
+}
+
+#[test]
+fn test_vector() {
+    use crate::{f1d, f2d, f3d, fnd, F1D, F2D, F3D};
+
+    let vars = ["r", "theta", "phi"];
+    let spherical = FVec::new(
+        &[
+            "r sin(theta)cos(phi)",
+            "r sin(theta)sin(phi)",
+            "r cos(theta)",
+        ],
+        &vars,
+    )
+    .unwrap();
+    assert_eq!(spherical.len(), 3);
+    let point = [("r", 2.), ("theta", 0.7), ("phi", -1.1)];
+    let det = spherical.jacobian_det();
+    let expected = 4. * 0.7f64.sin();
+    assert!((det.eval(&point) - expected).abs() < 1e-12);
+
+    let jacobian = spherical.jacobian();
+    let values = spherical.eval_jacobian(&point);
+    for i in 0..3 {
+        for j in 0..3 {
+            assert!((jacobian[i][j].eval(&point) - values[i][j]).abs() < 1e-12);
+        }
+    }
+    assert_eq!(jacobian[2][1], fnd!("-r sin(theta)", &vars));
+
+    let f = FVec::from(vec![f2d!("x^2y"), f2d!("x-y")]);
+    assert_eq!(f.eval(&[("x", 2.), ("y", 3.)]), vec![12., -1.]);
+    assert_eq!(f.jacobian_det(), fnd!("-2xy-x^2", &["x", "y"]));
+    assert_eq!(
+        FVec::from(vec![f1d!("x^3")]).jacobian_det(),
+        fnd!("3x^2", &["x"])
+    );
+    assert_eq!(
+        FVec::from(vec![f3d!("x"), f3d!("y"), f3d!("z")]).jacobian_det(),
+        fnd!("1", &["x", "y", "z"])
+    );
+    let f = FVec::try_from(vec![fnd!("a+b", &["a", "b"]), fnd!("ab", &["a", "b"])]).unwrap();
+    assert_eq!(f.component(1), fnd!("ab", &["a", "b"]));
+    assert_eq!(f.vars(), ["a", "b"]);
+    assert_eq!(
+        FVec::try_from(vec![fnd!("a", &["a"]), fnd!("b", &["b"])]),
+        Err(MismatchedVars {
+            expected: vec!["a".to_string()],
+            found: vec!["b".to_string()]
+        })
+    );
+}